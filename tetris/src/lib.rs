@@ -1,13 +1,63 @@
 pub mod tetris {
-    use rand::seq::SliceRandom;
-    use rand::thread_rng;
+    use rand::{thread_rng, Rng};
+    use serde::{Deserialize, Serialize};
     use std::collections::VecDeque;
     use std::fmt::Display;
-    use strum::IntoEnumIterator;
     use strum_macros::EnumIter;
 
-    pub const MAX_ROW: usize = 40;
-    pub const MAX_COL: usize = 10;
+    mod attack;
+    pub use attack::{AttackKind, AttackTable, GuidelineAttack, TetrioAttack};
+    mod board;
+    mod bot;
+    pub use board::{Board, BoardError, MIN_BUFFER};
+    pub use bot::{Bot, Features, Weights};
+    mod events;
+    pub use events::{GameEvent, GameOverReason};
+    mod finesse;
+    pub use finesse::{Finesse, FinesseFault};
+    mod garbage;
+    use garbage::GarbageQueue;
+    pub use garbage::GARBAGE_CELL;
+    mod handling;
+    pub use handling::Handling;
+    use handling::HeldInputs;
+    mod lock;
+    pub use lock::{LockReset, DEFAULT_LOCK_DELAY, MOVE_RESET_LIMIT};
+    mod mode;
+    mod movegen;
+    pub use movegen::Placement;
+    mod net;
+    pub use mode::{
+        format_time, Endless, GameMode, GameModeKind, Marathon, Sprint, Ultra, Zen, MARATHON_LINES,
+        SPRINT_LINES, ULTRA_FRAMES,
+    };
+    pub use net::{
+        Client, ClientMessage, MatchState, OnlineGame, Server, ServerMessage, DEFAULT_COUNTDOWN,
+    };
+    mod randomizer;
+    pub use randomizer::{
+        Bag, History, Memoryless, Nes, Randomizer, RandomizerKind, SavedRandomizer,
+    };
+    mod replay;
+    pub use replay::{Input, Playback, Replay, REPLAY_VERSION};
+    mod rotation;
+    pub use rotation::{Ars, Nrs, RotationSystem, RotationSystemKind, Sega, Srs, SrsPlus};
+    mod save;
+    mod scoring;
+    pub use save::SAVE_VERSION;
+    pub use scoring::{
+        Clear, GuidelineScoring, NesScoring, SavedScoring, ScoringKind, ScoringSystem, SegaScoring,
+        TgmScoring,
+    };
+    mod spin;
+    pub use spin::SpinRule;
+    mod stats;
+    pub use stats::Stats;
+    mod tbp;
+    pub use tbp::{
+        serve_tbp, BotMessage, FrontendMessage, Orientation, PieceLocation, TbpBot, TbpMove,
+        TbpSpin, TbpStart,
+    };
 
     /// A Tetromino is a tetromino in tetris. They are all made up of exactly 4 blocks.
     /// It can be one of 7 different variants:
@@ -16,7 +66,7 @@ pub mod tetris {
     /// - `T` Pieces.
     /// - `L`/`J` Pieces.
     /// - `S`/`Z` Pieces, also called "skew".
-    #[derive(Debug, Clone, Copy, EnumIter, PartialEq, Eq)]
+    #[derive(Debug, Clone, Copy, EnumIter, PartialEq, Eq, Hash, Serialize, Deserialize)]
     pub enum Tetromino {
         I = 1,
         O = 2,
@@ -26,51 +76,6 @@ pub mod tetris {
         S = 6,
         Z = 7,
     }
-    impl Tetromino {
-        /// Gives the "shape" of a tetromino, given the default origin state is
-        /// at (0, 0).
-        fn shape(&self, rotation: State) -> [(i32, i32); 4] {
-            match self {
-                Tetromino::I => match rotation {
-                    State::Up => [(-1, 0), (0, 0), (1, 0), (2, 0)],
-                    State::Right => [(0, -1), (0, 0), (0, 1), (0, 2)],
-                    State::Down => [(-2, 0), (-1, 0), (0, 0), (1, 0)],
-                    State::Left => [(0, -2), (0, -1), (0, 0), (0, 1)],
-                },
-                Tetromino::O => [(0, -1), (1, -1), (0, 0), (1, 0)],
-                Tetromino::T => match rotation {
-                    State::Up => [(0, -1), (-1, 0), (0, 0), (1, 0)],
-                    State::Right => [(0, -1), (0, 0), (1, 0), (0, 1)],
-                    State::Down => [(-1, 0), (0, 0), (1, 0), (0, 1)],
-                    State::Left => [(0, -1), (-1, 0), (0, 0), (0, 1)],
-                },
-                Tetromino::J => match rotation {
-                    State::Up => [(-1, -1), (-1, 0), (0, 0), (1, 0)],
-                    State::Right => [(0, -1), (1, -1), (0, 0), (0, 1)],
-                    State::Down => [(-1, 0), (0, 0), (1, 0), (1, 1)],
-                    State::Left => [(0, 1), (0, 0), (-1, 1), (0, -1)],
-                },
-                Tetromino::L => match rotation {
-                    State::Up => [(1, -1), (-1, 0), (0, 0), (1, 0)],
-                    State::Right => [(0, -1), (0, 0), (0, 1), (1, 1)],
-                    State::Down => [(-1, 0), (0, 0), (1, 0), (-1, 1)],
-                    State::Left => [(-1, -1), (0, -1), (0, 0), (0, 1)],
-                },
-                Tetromino::S => match rotation {
-                    State::Up => [(0, -1), (1, -1), (-1, 0), (0, 0)],
-                    State::Right => [(0, -1), (0, 0), (1, 0), (1, 1)],
-                    State::Down => [(0, 0), (1, 0), (-1, 1), (0, 1)],
-                    State::Left => [(-1, -1), (-1, 0), (0, 0), (0, 1)],
-                },
-                Tetromino::Z => match rotation {
-                    State::Up => [(-1, -1), (0, -1), (0, 0), (1, 0)],
-                    State::Right => [(1, -1), (1, 0), (0, 0), (0, 1)],
-                    State::Down => [(-1, 0), (0, 0), (0, 1), (1, 1)],
-                    State::Left => [(0, -1), (0, 0), (-1, 0), (-1, 1)],
-                },
-            }
-        }
-    }
     impl Display for Tetromino {
         fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
             match self {
@@ -85,17 +90,24 @@ pub mod tetris {
         }
     }
 
-    #[derive(Debug, Clone, Copy, EnumIter, PartialEq, Eq)]
-    enum State {
+    /// The orientation of a piece. `Up` is the orientation pieces spawn in,
+    /// and the others are named for a quarter turn clockwise at a time.
+    #[derive(Debug, Clone, Copy, EnumIter, PartialEq, Eq, Hash, Serialize, Deserialize)]
+    pub enum State {
         Up,
         Right,
         Down,
         Left,
     }
     impl State {
+        /// The orientation after a half turn.
+        pub fn flip(&self) -> Self {
+            self.rotate(true).rotate(true)
+        }
+
         /// This returns the rotational enum for if you rotate either clockwise or
         /// counter-clockwise.
-        fn rotate(&self, clockwise: bool) -> Self {
+        pub fn rotate(&self, clockwise: bool) -> Self {
             if clockwise {
                 match self {
                     State::Up => State::Right,
@@ -113,113 +125,61 @@ pub mod tetris {
             }
         }
     }
-    /// A bag is a data structure used by Tetris to represent the queue of incoming
-    /// pieces.
-    struct Bag(Vec<Tetromino>);
-    impl Bag {
-        /// Creates a new bag with randomly shuffled Tetromino's. A bag always has
-        /// at most 7 tetromino's inside of it, one of each of the main pieces, as
-        /// to ensure that a player isn't constantly getting the same tetromino over
-        /// and over, but there is still an element of randomness.
-        fn new() -> Self {
-            let mut bag = Bag(Vec::with_capacity(7));
-            bag.fill();
-            bag
-        }
-        /// Fills an empty bag with randomly shuffled Tetromino's. If the bag isn't
-        /// empty, it does nothing, as it is unable to fill a bag already full.
-        /// Otherwise, the bag is filled up with shuffled elements.
-        fn fill(&mut self) {
-            if !self.0.is_empty() {
-                return;
-            }
-            for tetromino in Tetromino::iter() {
-                self.0.push(tetromino);
-            }
-            self.0.shuffle(&mut thread_rng());
-        }
-    }
-    impl Iterator for Bag {
-        type Item = Tetromino;
-
-        fn next(&mut self) -> Option<Self::Item> {
-            // We try to fill it regardless of anything...
-            self.fill();
-            // Then we simply pop from the top of the bag!
-            self.0.pop()
-        }
-    }
-
-    #[derive(Debug, Clone, Copy)]
+    #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
     pub struct Pos(usize, usize);
     impl Pos {
         pub fn new(row: usize, col: usize) -> Self {
-            // Asserting the pos is within bounds we want or else we panic.
-            assert_eq!(row < MAX_ROW, col < MAX_COL);
             Pos(row, col)
         }
         pub fn coords(&self) -> (usize, usize) {
             (self.0, self.1)
         }
+        /// Moves the position, as long as it doesn't go past the top or left of
+        /// the board. The bottom and right are left for the board to check.
         pub fn try_move(&self, x: i32, y: i32) -> Option<Self> {
             let (row, col) = (self.0 as i32, self.1 as i32);
-            if row + y < 0 || row + y >= MAX_ROW as i32 || col + x < 0 || col + x >= MAX_COL as i32
-            {
+            if row + y < 0 || col + x < 0 {
                 None
             } else {
                 Some(Pos((row + y) as usize, (col + x) as usize))
             }
         }
-        pub fn in_range(row: i32, col: i32) -> bool {
-            if row < 0 || col < 0 {
-                false
-            } else {
-                let (row, col) = (row as usize, col as usize);
-                row < MAX_ROW && col < MAX_COL
-            }
-        }
-
-        pub fn in_safe_range(row: usize, col: usize) -> bool {
-            (1..(MAX_ROW - 1)).contains(&row) && (1..(MAX_COL - 1)).contains(&col)
-        }
     }
 
-    #[derive(Debug, Clone, Copy)]
+    #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
     struct ActivePiece {
         tetromino: Tetromino,
         origin: Pos,
         rotation: State,
+        system: RotationSystemKind,
     }
     impl ActivePiece {
-        fn new(tetromino: Tetromino) -> Self {
+        /// Spawns a piece at the top of the visible part of the board, in the
+        /// middle (leaning left on even widths).
+        fn new(tetromino: Tetromino, board: &Board, system: RotationSystemKind) -> Self {
             ActivePiece {
                 tetromino,
-                origin: Pos::new(20, 4),
-                rotation: State::Up,
+                origin: Pos::new(board.buffer(), (board.width() - 1) / 2),
+                rotation: system.system().spawn_rotation(tetromino),
+                system,
             }
         }
 
         /// Gets the positions of the squares that the active piece represents.
         fn get_squares(&self) -> [(i32, i32); 4] {
             let (y, x) = (self.origin.0 as i32, self.origin.1 as i32);
-            self.tetromino
-                .shape(self.rotation)
-                .into_iter()
+            self.system
+                .system()
+                .shape(self.tetromino, self.rotation)
                 .map(|(a, b)| (y + b, x + a))
-                .collect::<Vec<(i32, i32)>>()
-                .try_into()
-                .unwrap()
         }
 
         /// Validates if a new state that we've passed in is valid within the
         /// board. If it is, we update the state and return true to signify that we
         /// updated.
-        fn validate(&mut self, new_state: &ActivePiece, board: &[[u8; MAX_COL]; MAX_ROW]) -> bool {
+        fn validate(&mut self, new_state: &ActivePiece, board: &Board) -> bool {
             for (row, col) in new_state.get_squares() {
-                if !Pos::in_range(row, col) {
-                    return false;
-                }
-                if board[row as usize][col as usize] != 0 {
+                if !board.is_empty_at(row, col) {
                     return false;
                 }
             }
@@ -228,115 +188,147 @@ pub mod tetris {
             true
         }
 
-        /// Tetris's rotational system is complex. To refer to it, please see
-        /// [the tetris wiki page on the subject](https://tetris.fandom.com/wiki/SRS#Wall_Kicks).
-        /// In short summary, each piece goes through 5 different tests when it
-        /// attempts to rotate - the 1st being the basic rotational state, and the
-        /// following 4 being various "wall kick" states. This is what enables
-        /// complex moves like [t-spins](https://tetris.com/article/70/how-to-perform-a-t-spin-in-tetris)
-        /// to properly work.
+        /// Rotates the piece a quarter turn, if it can be done. The piece's
+        /// rotation system gives the list of positions to try, and the first one
+        /// that fits is where the piece ends up. Returns which of the positions
+        /// that was, if it rotated.
         ///
         /// This function takes in a bool as to if it is going
         /// clockwise/counter-clockwise, and performs the rotation on itself if it
         /// can be successfully done.
-        fn rotate(&mut self, clockwise: bool, board: &[[u8; MAX_COL]; MAX_ROW]) -> bool {
+        fn rotate(&mut self, clockwise: bool, board: &Board) -> Option<usize> {
             // Getting our new rotational state.
             let new_rotation = self.rotation.rotate(clockwise);
-            let (row, col) = (self.origin.0 as i32, self.origin.1 as i32);
-            // These are the different "origin" states we will be testing.
-            let origin = if let Tetromino::I = self.tetromino {
-                match (clockwise, new_rotation) {
-                    (true, State::Up) | (false, State::Right) => (row - 1, col),
-                    (true, State::Right) | (false, State::Down) => (row, col + 1),
-                    (true, State::Down) | (false, State::Left) => (row + 1, col),
-                    (true, State::Left) | (false, State::Up) => (row, col - 1),
-                }
-            } else {
-                (row, col)
-            };
-            let mut origins = vec![origin];
-            // "Kick data" refers to the possible offset values that can be used for the 4 kick states.
-            // There are 8 total different offset value sets; 4 of which are inverted from the other 4.
-            // Refers to the I Tetromino.
-            let kick_data_i1 = vec![(-2, 0), (1, 0), (-2, -1), (1, -2)];
-            let kick_data_i2 = vec![(-1, 0), (2, 0), (-1, -2), (2, 1)];
-            // Refers to the other 5 (non-O) Tetrominos.
-            let kick_data = vec![(-1, 0), (-1, -1), (0, 2), (-1, 2)];
-            // We extend our possible tests with the 4 additional tests:
-            origins.extend(
-                match self.tetromino {
-                    Tetromino::O => return false, /* O Tetromino's have no rotational logic. */
-                    Tetromino::I => match (self.rotation, new_rotation) {
-                        // CW from Spawn State OR CCW to Inverted Spawn State
-                        (State::Up, State::Right) | (State::Left, State::Down) => kick_data_i1,
-                        // CCW to Spawn State OR CW from Inverted Spawn State
-                        (State::Right, State::Up) | (State::Down, State::Left) => {
-                            kick_data_i1.into_iter().map(|(x, y)| (-x, -y)).collect()
-                        }
-                        // CW to Inverted Spawn State OR CCW from Spawn State
-                        (State::Right, State::Down) | (State::Up, State::Left) => kick_data_i2,
-                        // CCW to Inverted Spawn State OR CW to Spawn State
-                        (State::Down, State::Right) | (State::Left, State::Up) => {
-                            kick_data_i2.into_iter().map(|(x, y)| (-x, -y)).collect()
-                        }
-                        _ => unreachable!(), /* THIS SHOULD NEVER HAPPEN. */
-                    },
-                    _ => match (self.rotation, new_rotation) {
-                        // CW from Spawn State OR CCW to Inverted Spawn State
-                        (State::Up, State::Right) | (State::Down, State::Right) => kick_data,
-                        // CCW to Spawn State OR CW from Inverted Spawn State
-                        (State::Right, State::Up) | (State::Right, State::Down) => {
-                            kick_data.into_iter().map(|(x, y)| (-x, -y)).collect()
-                        }
-                        // CW from Inverted Spawn State OR CW to Spawn State
-                        (State::Down, State::Left) | (State::Left, State::Up) => {
-                            kick_data.into_iter().map(|(x, y)| (-x, y)).collect()
-                        }
-                        // CCW to Inverted Spawn State OR CCW from Spawn State
-                        (State::Left, State::Down) | (State::Up, State::Left) => {
-                            kick_data.into_iter().map(|(x, y)| (x, -y)).collect()
-                        }
-                        _ => unreachable!(), /* THIS SHOULD NEVER HAPPEN. */
-                    },
-                }
-                .into_iter()
-                .map(|(x, y)| (row + y, col + x)),
+            let kicks = self.system.system().kicks(
+                self.tetromino,
+                self.rotation,
+                new_rotation,
+                self.origin,
+                board,
             );
-            // Turning these into Positions (when they're possible).
-            let tests = origins.into_iter().flat_map(|(row, col)| {
-                if Pos::in_range(row, col) {
-                    Some(Pos(row as usize, col as usize))
-                } else {
-                    None
-                }
-            });
+            self.try_rotations(new_rotation, kicks, board)
+        }
+
+        /// Turns the piece a half turn, using its rotation system's 180 kicks.
+        fn rotate_180(&mut self, board: &Board) -> Option<usize> {
+            let new_rotation = self.rotation.flip();
+            let kicks = self.system.system().kicks_180(
+                self.tetromino,
+                self.rotation,
+                new_rotation,
+                self.origin,
+                board,
+            );
+            self.try_rotations(new_rotation, kicks, board)
+        }
+
+        /// Moves the piece into `new_rotation` at the first of the `kicks`
+        /// that fits, if any of them do, and returns the index of that kick.
+        fn try_rotations(
+            &mut self,
+            new_rotation: State,
+            kicks: Vec<(i32, i32)>,
+            board: &Board,
+        ) -> Option<usize> {
             // Attempting all of our tests.
-            for new_pos in tests {
-                // Returning if we've successfully validated a given state!
-                if self.validate(
-                    &ActivePiece {
-                        tetromino: self.tetromino,
-                        origin: new_pos,
-                        rotation: new_rotation,
-                    },
-                    board,
-                ) {
-                    return true;
+            for (i, (x, y)) in kicks.into_iter().enumerate() {
+                if let Some(new_pos) = self.origin.try_move(x, y) {
+                    // Returning if we've successfully validated a given state!
+                    if self.validate(
+                        &ActivePiece {
+                            origin: new_pos,
+                            rotation: new_rotation,
+                            ..*self
+                        },
+                        board,
+                    ) {
+                        return Some(i);
+                    }
                 }
             }
-            false
+            None
+        }
+
+        /// If the piece would still fit on the board if it moved by `(x, y)`.
+        fn can_move(&self, x: i32, y: i32, board: &Board) -> bool {
+            self.get_squares()
+                .into_iter()
+                .all(|(row, col)| board.is_empty_at(row + y, col + x))
+        }
+
+        /// Finds the centre cell of a `T` piece, as `(row, col)`, and the
+        /// direction its nub points in, as `(d_row, d_col)`. Each rotation system
+        /// places the `T` differently relative to its origin, so this is worked
+        /// out from the cells themselves.
+        fn t_facing(&self) -> ((i32, i32), (i32, i32)) {
+            let squares = self.get_squares();
+            let is_square = |r: i32, c: i32| squares.contains(&(r, c));
+            // The centre is the only cell touching all three others.
+            let (row, col) = squares
+                .into_iter()
+                .find(|&(r, c)| {
+                    [(r - 1, c), (r + 1, c), (r, c - 1), (r, c + 1)]
+                        .into_iter()
+                        .filter(|&(r, c)| is_square(r, c))
+                        .count()
+                        == 3
+                })
+                .unwrap();
+            // The nub is the neighbour without a neighbour opposite it.
+            let facing = [(-1, 0), (1, 0), (0, -1), (0, 1)]
+                .into_iter()
+                .find(|&(dr, dc)| !is_square(row - dr, col - dc))
+                .unwrap();
+            ((row, col), facing)
+        }
+
+        /// Checks if the piece rotating into where it is now on `board` was a
+        /// spin, returning the kind of spin it was under `rule`.
+        fn detect_spin(&self, board: &Board, rule: SpinRule, final_kick: bool) -> SpinType {
+            let tetromino = self.tetromino;
+            let immobile = [(-1, 0), (1, 0), (0, -1)]
+                .into_iter()
+                .all(|(x, y)| !self.can_move(x, y, board));
+            match (tetromino, rule) {
+                (Tetromino::T, _) => self.detect_t_spin(board, final_kick),
+                (Tetromino::O, _) | (_, SpinRule::TSpin) => SpinType::Not,
+                _ if !immobile => SpinType::Not,
+                (_, SpinRule::AllMini) => SpinType::Mini(tetromino),
+                (_, SpinRule::AllSpin) => SpinType::Full(tetromino),
+            }
+        }
+
+        /// Checks for a T-spin with the Guideline's 3-corner rule. At least
+        /// three of the corners around the T's centre have to be filled,
+        /// counting the walls and floor as filled. It's a full T-spin if both
+        /// corners beside the nub are, and a mini otherwise, unless the rotation
+        /// needed the final kick, as in T-spin triples and fins.
+        fn detect_t_spin(&self, board: &Board, final_kick: bool) -> SpinType {
+            let ((row, col), (dr, dc)) = self.t_facing();
+            // The corners on either side of the T's nub are in "front", and the
+            // other two are behind it.
+            let filled = |r: i32, c: i32| !board.is_empty_at(r, c) as i32;
+            let front_count =
+                filled(row + dr + dc, col + dc + dr) + filled(row + dr - dc, col + dc - dr);
+            let back_count =
+                filled(row - dr + dc, col - dc + dr) + filled(row - dr - dc, col - dc - dr);
+            match (front_count, back_count) {
+                (front, back) if front + back < 3 => SpinType::Not,
+                (2, _) => SpinType::Full(Tetromino::T),
+                _ if final_kick => SpinType::Full(Tetromino::T),
+                _ => SpinType::Mini(Tetromino::T),
+            }
         }
 
         /// Attempt to move a piece down by 1 pos
         /// if successful, update active piece position, return `true`
         /// if not, return `false`
-        fn soft_drop(&mut self, board: &[[u8; MAX_COL]; MAX_ROW]) -> bool {
+        fn soft_drop(&mut self, board: &Board) -> bool {
             if let Some(new_pos) = self.origin.try_move(0, 1) {
                 return self.validate(
                     &ActivePiece {
-                        tetromino: self.tetromino,
                         origin: new_pos,
-                        rotation: self.rotation,
+                        ..*self
                     },
                     board,
                 );
@@ -344,13 +336,12 @@ pub mod tetris {
             false
         }
 
-        fn shift(&mut self, left: bool, board: &[[u8; MAX_COL]; MAX_ROW]) -> bool {
+        fn shift(&mut self, left: bool, board: &Board) -> bool {
             if let Some(pos) = self.origin.try_move(if left { -1 } else { 1 }, 0) {
                 return self.validate(
                     &ActivePiece {
-                        tetromino: self.tetromino,
                         origin: pos,
-                        rotation: self.rotation,
+                        ..*self
                     },
                     board,
                 );
@@ -359,21 +350,138 @@ pub mod tetris {
         }
     }
 
+    /// Every input a player can make on the game.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+    pub enum Action {
+        ShiftLeft,
+        ShiftRight,
+        RotateCw,
+        RotateCcw,
+        Rotate180,
+        SoftDrop,
+        HardDrop,
+        Hold,
+    }
+
+    /// What came of applying an [`Action`].
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum Outcome {
+        /// The piece moved, or was swapped with the held piece.
+        Moved,
+        /// Nothing happened, as the piece couldn't move or be held.
+        Blocked,
+        /// The piece locked onto the board.
+        Locked,
+        /// The piece rotated into a spin.
+        Spin(SpinType),
+    }
+
+    /// Whether a piece was spun into place, and the piece that was.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
     pub enum SpinType {
         Not,
-        Full,
-        Mini,
+        Full(Tetromino),
+        Mini(Tetromino),
     }
 
+    /// Everything about how a game is set up, that stays the same for the
+    /// whole game. Anything missing from a saved config takes its default.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(default)]
+    pub struct Config {
+        pub randomizer: RandomizerKind,
+        pub rotation: RotationSystemKind,
+        /// The number of columns on the board.
+        pub width: usize,
+        /// The number of rows the player can see.
+        pub height: usize,
+        /// The number of hidden rows above the visible ones.
+        pub buffer: usize,
+        /// What gives a piece resting on the stack more time before it locks.
+        pub lock_reset: LockReset,
+        /// How many frames a piece can rest on the stack before it locks, for
+        /// each level from 1 to 15.
+        pub lock_delay: [u8; 15],
+        /// How held inputs repeat.
+        pub handling: Handling,
+        pub scoring: ScoringKind,
+        /// Which pieces can spin.
+        pub spins: SpinRule,
+        /// What the game is played for, and how it ends.
+        pub mode: GameModeKind,
+        /// The chance, as a percentage, that each line of garbage after the
+        /// first in a batch has its hole in a different column.
+        pub messiness: u8,
+        /// How many lines of garbage clears send to an opponent.
+        pub attack: AttackKind,
+    }
+    impl Default for Config {
+        fn default() -> Self {
+            Config {
+                randomizer: RandomizerKind::default(),
+                rotation: RotationSystemKind::default(),
+                width: 10,
+                height: 20,
+                buffer: 20,
+                lock_reset: LockReset::default(),
+                lock_delay: [DEFAULT_LOCK_DELAY; 15],
+                handling: Handling::default(),
+                scoring: ScoringKind::default(),
+                spins: SpinRule::default(),
+                mode: GameModeKind::default(),
+                messiness: 0,
+                attack: AttackKind::default(),
+            }
+        }
+    }
+    impl Config {
+        /// The lock delay, in frames, at the given level.
+        pub fn lock_delay_at(&self, level: u32) -> u8 {
+            let i = (level.max(1) as usize - 1).min(self.lock_delay.len() - 1);
+            self.lock_delay[i]
+        }
+    }
+
+    #[derive(Serialize, Deserialize)]
     pub struct Tetris {
-        board: [[u8; MAX_COL]; MAX_ROW],
+        board: Board,
         active: ActivePiece,
-        bag: Bag,
+        #[serde(with = "save::randomizer")]
+        randomizer: Box<dyn Randomizer>,
+        #[serde(with = "save::scoring")]
+        scoring: Box<dyn ScoringSystem>,
+        config: Config,
         held: (Option<Tetromino>, bool),
         queue: VecDeque<Tetromino>,
+        /// The frames that the active piece has spent resting on the stack.
         delay_count: u8,
+        /// The moves the active piece has left that will restart its lock delay.
+        resets_left: u8,
+        /// The lowest row the active piece's origin has reached.
+        lowest_row: usize,
+        #[serde(default)]
+        inputs: HeldInputs,
         gravity_count: f64,
         manually_set_level: bool,
+        seed: u64,
+        frame: u64,
+        recording: Option<Replay>,
+        /// Everything that's happened since the events were last taken.
+        #[serde(skip)]
+        events: Vec<GameEvent>,
+        /// The back-to-back chain, if the last clear was a difficult one; how
+        /// many difficult clears have been made back-to-back since.
+        #[serde(default)]
+        b2b_chain: Option<u32>,
+        /// Garbage waiting to rise up from the bottom of the board.
+        #[serde(default)]
+        garbage: GarbageQueue,
+        /// How cleanly the pieces have been put in place so far.
+        #[serde(default)]
+        finesse: Finesse,
+        /// What's been done over the game so far.
+        #[serde(default)]
+        stats: Stats,
         pub last_was_spin: SpinType,
         pub did_tetris: bool,
         pub combo_count: i32,
@@ -384,88 +492,106 @@ pub mod tetris {
     }
     impl Default for Tetris {
         fn default() -> Self {
-            let board = [[0; MAX_COL]; MAX_ROW];
-            let mut bag = Bag::new();
-            // Setting the active piece.
-            let active = ActivePiece::new(bag.next().unwrap());
-
-            // The queue is always a size of 4, and contains the next 4 tetrominos
-            // from the bag, after the initial piece.
-            let mut queue = VecDeque::with_capacity(4);
-            while queue.len() < 4 {
-                if let Some(t) = bag.next() {
-                    queue.push_back(t);
-                }
-            }
-            Tetris {
-                board,
-                active,
-                bag,
-                held: (None, false),
-                queue,
-                delay_count: 0,
-                gravity_count: 0.0,
-                last_was_spin: SpinType::Not,
-                combo_count: -1,
-                manually_set_level: false,
-                did_tetris: false,
-                score: 0,
-                level: 1,
-                lines: 0,
-                is_game_over: false,
-            }
+            Tetris::new(None, None, None)
         }
     }
 
-    /// The number of frames we want to wait before trying to lock a piece.
-    const LOCK_DELAY: u8 = 30;
     impl Tetris {
         pub fn new(
-            provided_board: Option<[[u8; MAX_COL]; MAX_ROW]>,
+            provided_board: Option<Board>,
             active_piece: Option<Tetromino>,
+            seed: Option<u64>,
         ) -> Self {
             // For testing purposes, allows setting board to arbitrary state
             /*
-               Similar to default(), this method initialized a Tetris object, however it allows pre-set board and
-               active_piece options. Not using these parameters will result in identical results to calling default().
+               Similar to default(), this method initialized a Tetris object, however it allows pre-set board,
+               active_piece and seed options. Not using these parameters will result in identical results to
+               calling default().
             */
-            let board;
-            if let Some(b) = provided_board {
-                // If arg for board is provided, use it.
-                board = b;
-            } else {
-                board = [[0; MAX_COL]; MAX_ROW];
-            }
+            // Without a seed we pick one at random, but we still keep hold of it
+            // so that the game can be reproduced later on.
+            let board = provided_board.unwrap_or_default();
+            Tetris::with_board(board, active_piece, Config::default(), seed)
+        }
+
+        /// Creates a new game set up with `config`, but starting from a board
+        /// that might already have blocks on it, and optionally the first piece.
+        /// The board's size is used in place of the one in the config.
+        pub fn with_board(
+            board: Board,
+            active_piece: Option<Tetromino>,
+            config: Config,
+            seed: Option<u64>,
+        ) -> Self {
+            let seed = seed.unwrap_or_else(|| thread_rng().gen());
+            let config = Config {
+                width: board.width(),
+                height: board.visible(),
+                buffer: board.buffer(),
+                ..config
+            };
+            Tetris::build(board, active_piece, seed, config)
+        }
+
+        /// Creates a new game that deals its pieces with the given kind of
+        /// randomizer. A random seed is chosen if one isn't provided.
+        pub fn with_randomizer(kind: RandomizerKind, seed: Option<u64>) -> Self {
+            let config = Config {
+                randomizer: kind,
+                ..Config::default()
+            };
+            Tetris::with_config(config, seed)
+        }
+
+        /// Creates a new, empty game set up with `config`. A random seed is
+        /// chosen if one isn't provided.
+        pub fn with_config(config: Config, seed: Option<u64>) -> Self {
+            let seed = seed.unwrap_or_else(|| thread_rng().gen());
+            let board = Board::new(config.width, config.height, config.buffer);
+            Tetris::build(board, None, seed, config)
+        }
 
-            let mut bag = Bag::new();
+        fn build(board: Board, active_piece: Option<Tetromino>, seed: u64, config: Config) -> Self {
+            let mut randomizer = config.randomizer.build(seed);
 
             let active = if let Some(t) = active_piece {
                 // If arg for first piece is provided, use it.
-                ActivePiece::new(t)
+                ActivePiece::new(t, &board, config.rotation)
             } else {
-                ActivePiece::new(bag.next().unwrap())
+                ActivePiece::new(randomizer.next_piece(), &board, config.rotation)
             };
 
             // The queue is always a size of 4, and contains the next 4 tetrominos
-            // from the bag, after the initial piece.
+            // from the randomizer, after the initial piece.
             let mut queue = VecDeque::with_capacity(4);
             while queue.len() < 4 {
-                if let Some(t) = bag.next() {
-                    queue.push_back(t);
-                }
+                queue.push_back(randomizer.next_piece());
             }
             Tetris {
                 board,
                 active,
                 held: (None, false),
                 queue,
-                bag,
+                randomizer,
+                scoring: config.scoring.build(),
+                config,
                 delay_count: 0,
+                resets_left: MOVE_RESET_LIMIT,
+                lowest_row: active.origin.0,
+                inputs: HeldInputs::default(),
                 gravity_count: 0.0,
                 last_was_spin: SpinType::Not,
                 combo_count: -1,
                 did_tetris: false,
                 manually_set_level: false,
+                seed,
+                frame: 0,
+                recording: None,
+                events: vec![GameEvent::PieceSpawned(active.tetromino)],
+                b2b_chain: None,
+                garbage: GarbageQueue::new(seed),
+                finesse: Finesse::default(),
+                stats: Stats::default(),
                 score: 0,
                 level: 1,
                 lines: 0,
@@ -473,7 +599,112 @@ pub mod tetris {
             }
         }
 
+        /// Creates a new game whose piece sequence is entirely determined by
+        /// `seed`. Two games made with the same seed will always be dealt the
+        /// same pieces, regardless of the machine they are played on.
+        pub fn with_seed(seed: u64) -> Self {
+            Tetris::new(None, None, Some(seed))
+        }
+
+        /// The setup that this game was created with.
+        pub fn get_config(&self) -> Config {
+            self.config
+        }
+
+        /// The grade the score is worth, if the game's scoring system gives
+        /// them out.
+        pub fn get_grade(&self) -> Option<&'static str> {
+            self.scoring.grade(self.score)
+        }
+
+        /// The number of difficult clears (tetrises and spins that clear lines)
+        /// made back-to-back in the current chain. A single, double or triple
+        /// without a spin breaks the chain.
+        pub fn get_b2b_chain(&self) -> u32 {
+            self.b2b_chain.unwrap_or(0)
+        }
+
+        /// How cleanly the pieces have been put in place so far. See
+        /// [`Finesse`] for how they're judged.
+        pub fn get_finesse(&self) -> Finesse {
+            self.finesse
+        }
+
+        /// What's been done over the game so far, like the pieces placed and
+        /// the lines sent, along with the speeds they were done at.
+        pub fn get_stats(&self) -> Stats {
+            Stats {
+                frames: self.frame,
+                ..self.stats
+            }
+        }
+
+        /// The seed that this game's randomizer was created with.
+        pub fn get_seed(&self) -> u64 {
+            self.seed
+        }
+
+        /// The number of frames that have been advanced since the game began.
+        pub fn get_frame(&self) -> u64 {
+            self.frame
+        }
+
+        /// Starts recording every input into a replay. Recording should begin
+        /// before any input is made, as the replay is played back from the
+        /// start of the game.
+        pub fn record(&mut self) {
+            self.recording = Some(Replay::new(self.seed, self.config));
+        }
+
+        /// Stops recording, and hands back the replay of the game so far.
+        pub fn take_replay(&mut self) -> Option<Replay> {
+            let mut replay = self.recording.take()?;
+            replay.finish(self.frame);
+            Some(replay)
+        }
+
+        /// The replay being recorded, as it stands, if we're recording.
+        pub fn get_replay(&self) -> Option<&Replay> {
+            self.recording.as_ref()
+        }
+
+        /// Takes every event that's happened since this was last called, oldest
+        /// first.
+        pub fn take_events(&mut self) -> Vec<GameEvent> {
+            std::mem::take(&mut self.events)
+        }
+
+        /// Adds an input to the replay on the current frame, if we're recording.
+        fn log(&mut self, input: Input) {
+            if let Some(replay) = &mut self.recording {
+                replay.push(self.frame, input);
+            }
+        }
+
+        /// Sends the game `lines` lines of garbage. They rise up from the
+        /// bottom of the board once a piece locks without clearing any lines,
+        /// while clearing lines cancels them out. The hole in each line is picked
+        /// by the game's own RNG, so the same seed always gets the same holes.
+        ///
+        /// No more than the board's height is taken at once, as that's enough
+        /// to top out from an empty board.
+        pub fn queue_garbage(&mut self, lines: u32) {
+            if self.is_game_over {
+                return;
+            }
+            let lines = lines.min(self.board.height() as u32);
+            self.log(Input::Garbage(lines));
+            let width = self.board.width();
+            self.garbage.push(lines, width, self.config.messiness);
+        }
+
+        /// The lines of garbage waiting to rise.
+        pub fn get_pending_garbage(&self) -> u32 {
+            self.garbage.len()
+        }
+
         pub fn set_level(&mut self, level: u32) {
+            self.log(Input::SetLevel(level));
             self.manually_set_level = true;
             if level >= 15 {
                 self.level = 15;
@@ -482,8 +713,11 @@ pub mod tetris {
             }
         }
 
-        pub fn get_state(&self) -> [[u8; MAX_COL]; MAX_ROW] {
-            let mut b_clone = self.board;
+        /// The board as the player sees it, with the active and ghost pieces
+        /// drawn onto it.
+        pub fn get_state(&self) -> Board {
+            let mut b_clone = self.board.clone();
+            let top = self.board.buffer();
             let mut ghost = self.active;
             while ghost.soft_drop(&self.board) {}
             let (g_squares, a_squares) = (ghost.get_squares(), self.active.get_squares());
@@ -491,11 +725,11 @@ pub mod tetris {
                 let ((g_r, g_c), (a_r, a_c)) = (g_squares[i], a_squares[i]);
                 let (g_r, g_c, a_r, a_c) = (g_r as usize, g_c as usize, a_r as usize, a_c as usize);
                 // The ghost piece first.
-                if g_r >= 20 {
+                if g_r >= top {
                     b_clone[g_r][g_c] = 8;
                 }
                 // Then the active piece.
-                if a_r >= 20 {
+                if a_r >= top {
                     b_clone[a_r][a_c] = self.active.tetromino as u8;
                 }
             }
@@ -510,9 +744,10 @@ pub mod tetris {
             }
         }
 
-        pub fn get_queue(&self) -> [Tetromino; 4] {
-            let queue = self.queue.clone();
-            queue.into_iter().collect::<Vec<_>>().try_into().unwrap()
+        /// The pieces coming up next, soonest first. Games deal four ahead,
+        /// but one set up by a bot frontend knows as many as it was told.
+        pub fn get_queue(&self) -> Vec<Tetromino> {
+            self.queue.iter().copied().collect()
         }
 
         /// This advances forward the game by a singular frame.
@@ -529,108 +764,140 @@ pub mod tetris {
         /// ```
         ///
         /// Where `time` refers to the amount of time spent in a single cell.
+        ///
+        /// Nothing happens once the game is over, so it stays as it ended.
         pub fn frame_advance(&mut self) {
+            if self.is_game_over {
+                return;
+            }
+            self.frame += 1;
             // Computes the "gravity" of the current level.
             let l = self.level as f64 - 1.0;
             let time = f64::powf(0.8 - (l * 0.007), l);
-            self.gravity_count += 1.0 / (time * 60.0);
-            // Computing the number of frames delayed by
-            self.delay_count += 1;
-            self.delay_count %= 60;
-            // checking on a clone of the board to see if we can soft drop...
-            let mut a_clone = self.active;
-            if !a_clone.soft_drop(&self.board.clone()) {
-                self.try_lock(true);
+            let gravity = 1.0 / (time * 60.0);
+            // A held soft drop speeds gravity up.
+            let (soft_drop, sdf) = (self.inputs.soft_drop, self.config.handling.sdf);
+            if soft_drop && sdf == 0 {
+                while self.active.soft_drop(&self.board) {
+                    self.last_was_spin = SpinType::Not;
+                    self.score += self.scoring.soft_drop(1, self.level);
+                }
+            } else if soft_drop {
+                self.gravity_count += gravity * sdf as f64;
             } else {
-                self.delay_count = 0;
+                self.gravity_count += gravity;
+            }
+            self.auto_shift();
+            // Counting how long the piece has rested on the stack, and locking it
+            // once that's longer than the lock delay.
+            if self.is_grounded() {
+                self.delay_count = self.delay_count.saturating_add(1);
+                if self.config.lock_reset != LockReset::Infinite
+                    && self.delay_count >= self.config.lock_delay_at(self.level)
+                {
+                    self.lock();
+                }
             }
             // Getting the total number of cells we need to advance...
             for _ in 0..self.gravity_count as u8 {
                 self.gravity_count -= 1.0;
-                // Trying to drop the piece - if not, we try to lock it.
-                self.active.soft_drop(&self.board);
+                if self.active.soft_drop(&self.board) {
+                    self.last_was_spin = SpinType::Not;
+                    if soft_drop {
+                        self.score += self.scoring.soft_drop(1, self.level);
+                    }
+                }
+            }
+            self.check_lowest();
+            self.check_ending();
+        }
+
+        /// If the active piece can't fall any further.
+        fn is_grounded(&self) -> bool {
+            let mut cloned = self.active;
+            !cloned.soft_drop(&self.board)
+        }
+
+        /// Gives the active piece a fresh lock delay if it has fallen lower than
+        /// it has been before.
+        fn check_lowest(&mut self) {
+            if self.active.origin.0 > self.lowest_row {
+                self.lowest_row = self.active.origin.0;
+                self.delay_count = 0;
+                self.resets_left = MOVE_RESET_LIMIT;
+            }
+        }
+
+        /// Applies the lock reset policy after a shift or rotation, given whether
+        /// the piece actually moved. Returns `true` if that locked the piece.
+        fn after_move(&mut self, moved: bool) -> bool {
+            self.check_lowest();
+            if !moved {
+                return false;
+            }
+            match self.config.lock_reset {
+                LockReset::Move if self.resets_left > 0 => {
+                    self.resets_left -= 1;
+                    self.delay_count = 0;
+                }
+                LockReset::Move => {
+                    if self.is_grounded() {
+                        self.lock();
+                        return true;
+                    }
+                }
+                LockReset::Step => {}
+                LockReset::Infinite => self.delay_count = 0,
+            }
+            false
+        }
+
+        /// Makes a single input, and reports what came of it. Every way of
+        /// playing the game, from the TUI to replays and bots, goes through here.
+        /// Once the game is over, every input is blocked.
+        pub fn apply(&mut self, action: Action) -> Outcome {
+            if self.is_game_over {
+                return Outcome::Blocked;
             }
+            self.log(Input::Action(action));
+            self.press(action);
+            self.perform(action)
         }
 
         /// Call the active piece's soft_drop() to update its position if possible.
         /// If not, write piece to game board and draw new piece.
         pub fn soft_drop(&mut self) {
-            self.score += 1;
-            if !self.active.soft_drop(&self.board) {
-                self.lock();
-            }
+            self.apply(Action::SoftDrop);
         }
 
         /// Immediately drop piece as far as it will go, and solidify at final
         /// position.
         pub fn hard_drop(&mut self) {
-            while self.active.soft_drop(&self.board) {
-                self.score += 2;
-            }
-            self.lock();
+            self.apply(Action::HardDrop);
         }
 
         /// Call the active piece's rotate()
         pub fn rotate(&mut self, clockwise: bool) {
-            let succ = self.active.rotate(clockwise, &self.board);
-
-            if succ && self.active.tetromino == Tetromino::T {
-                let new_pos = self.active.origin;
-                let front_count = match self.active.rotation {
-                    State::Up => {
-                        (self.board[new_pos.0 - 1][new_pos.1 - 1] != 0) as i32
-                            + (self.board[new_pos.0 + 1][new_pos.1 - 1] != 0) as i32
-                    }
-                    State::Right => {
-                        (self.board[new_pos.0 + 1][new_pos.1 - 1] != 0) as i32
-                            + (self.board[new_pos.0 + 1][new_pos.1 + 1] != 0) as i32
-                    }
-                    State::Down => {
-                        (self.board[new_pos.0 - 1][new_pos.1 + 1] != 0) as i32
-                            + (self.board[new_pos.0 + 1][new_pos.1 + 1] != 0) as i32
-                    }
-                    State::Left => {
-                        (self.board[new_pos.0 - 1][new_pos.1 - 1] != 0) as i32
-                            + (self.board[new_pos.0 - 1][new_pos.1 + 1] != 0) as i32
-                    }
-                };
-
-                let back_count = if !Pos::in_safe_range(new_pos.0, new_pos.1) {
-                    2
-                } else {
-                    match self.active.rotation {
-                        State::Up => {
-                            (self.board[new_pos.0 - 1][new_pos.1 + 1] != 0) as i32
-                                + (self.board[new_pos.0 + 1][new_pos.1 + 1] != 0) as i32
-                        }
-                        State::Right => {
-                            (self.board[new_pos.0 - 1][new_pos.1 - 1] != 0) as i32
-                                + (self.board[new_pos.0 - 1][new_pos.1 + 1] != 0) as i32
-                        }
-                        State::Down => {
-                            (self.board[new_pos.0 - 1][new_pos.1 - 1] != 0) as i32
-                                + (self.board[new_pos.0 + 1][new_pos.1 - 1] != 0) as i32
-                        }
-                        State::Left => {
-                            (self.board[new_pos.0 + 1][new_pos.1 - 1] != 0) as i32
-                                + (self.board[new_pos.0 + 1][new_pos.1 + 1] != 0) as i32
-                        }
-                    }
-                };
+            self.apply(if clockwise {
+                Action::RotateCw
+            } else {
+                Action::RotateCcw
+            });
+        }
 
-                if front_count == 2 && back_count == 2 {
-                    self.last_was_spin = SpinType::Full;
-                } else if front_count == 1 && back_count == 2 {
-                    self.last_was_spin = SpinType::Mini;
-                }
-            }
-            self.try_lock(false);
+        /// Turns the piece a half turn in one go, using the rotation system's
+        /// 180 kicks.
+        pub fn rotate_180(&mut self) {
+            self.apply(Action::Rotate180);
         }
 
         /// Shifts a piece to the left/right.
         pub fn shift(&mut self, left: bool) {
-            self.active.shift(left, &self.board);
-            self.try_lock(false);
+            self.apply(if left {
+                Action::ShiftLeft
+            } else {
+                Action::ShiftRight
+            });
         }
 
         /// Hold functionality
@@ -639,82 +906,335 @@ pub mod tetris {
         ///
         /// A piece can only be removed from held once a lock has occurred.
         pub fn hold(&mut self) {
-            if let (Some(tetromino), true) = self.held {
-                self.held = (Some(self.active.tetromino), false);
-                self.active = ActivePiece::new(tetromino);
-            } else if self.held.0.is_none() {
-                self.held = (Some(self.active.tetromino), false);
-                self.active = ActivePiece::new(self.next_piece());
+            self.apply(Action::Hold);
+        }
+
+        /// Presses or releases an input, for frontends that know when keys go up
+        /// as well as down. Shifts and soft drops repeat for as long as they're
+        /// held, following the game's [`Handling`], while every other action
+        /// happens once when it's pressed.
+        pub fn set_held(&mut self, action: Action, held: bool) {
+            if self.is_game_over {
+                return;
+            }
+            self.log(Input::Held(action, held));
+            if held {
+                self.press(action);
+            }
+            match action {
+                Action::ShiftLeft | Action::ShiftRight => {
+                    let left = action == Action::ShiftLeft;
+                    if left {
+                        self.inputs.left = held;
+                    } else {
+                        self.inputs.right = held;
+                    }
+                    if held {
+                        self.inputs.repeat(Some(left));
+                        self.perform(action);
+                    } else if self.inputs.direction == Some(left) {
+                        // Letting go goes back to the other direction, if that's
+                        // still being held.
+                        let other = if left {
+                            self.inputs.right
+                        } else {
+                            self.inputs.left
+                        };
+                        self.inputs.repeat(other.then_some(!left));
+                    }
+                }
+                Action::SoftDrop => self.inputs.soft_drop = held,
+                _ if held => {
+                    self.perform(action);
+                }
+                _ => {}
             }
         }
 
-        /// Return the next piece in the queue and pull a new piece
-        /// from the bag to replace it
-        fn next_piece(&mut self) -> Tetromino {
-            let popped = self.queue.pop_front();
-            if let Some(tet) = self.bag.next() {
-                self.queue.push_back(tet);
+        /// Lets go of every input that's being held, like when the game is
+        /// paused and its key releases could go missing.
+        pub fn release_all(&mut self) {
+            let HeldInputs {
+                left,
+                right,
+                soft_drop,
+                ..
+            } = self.inputs;
+            for (action, held) in [
+                (Action::ShiftLeft, left),
+                (Action::ShiftRight, right),
+                (Action::SoftDrop, soft_drop),
+            ] {
+                if held {
+                    self.set_held(action, false);
+                }
             }
-            popped.unwrap()
         }
 
-        /// `try_lock` attempts to lock the piece onto the board. It takes in a bool
-        /// specifying whether or not we want to force the locking of the piece - as
-        /// for certain moves (such as T-spins and hard drops) we want this to occur.
-        fn try_lock(&mut self, on_frame: bool) {
-            if self.delay_count >= LOCK_DELAY && on_frame {
-                // Piece's won't lock if they're not being forced to and they're under
-                // the required frame count.
-                self.lock();
+        /// Counts a key pressed by the player. Repeats don't count.
+        fn press(&mut self, action: Action) {
+            self.stats.keys += 1;
+            self.finesse.press(action);
+        }
+
+        /// Repeats the held direction once the DAS has charged, every ARR frames.
+        fn auto_shift(&mut self) {
+            let Some(left) = self.inputs.direction else {
+                return;
+            };
+            let Handling { das, arr, .. } = self.config.handling;
+            if self.inputs.das_charge < das {
+                self.inputs.das_charge += 1;
+                if self.inputs.das_charge < das {
+                    return;
+                }
+            } else {
+                self.inputs.arr_count += 1;
+                if self.inputs.arr_count < arr {
+                    return;
+                }
+            }
+            self.inputs.arr_count = 0;
+            if arr == 0 {
+                // An ARR of 0 goes straight to the wall.
+                while self.active.shift(left, &self.board) {
+                    self.last_was_spin = SpinType::Not;
+                    if self.after_move(true) {
+                        break;
+                    }
+                }
             } else {
-                // Here we check to see if the piece is immobile. If it is, we lock it.
-                for i in 0..4 {
-                    let mut cloned = self.active;
-                    let v = match i {
-                        0 => cloned.shift(true, &self.board),
-                        1 => cloned.shift(false, &self.board),
-                        2 => cloned.rotate(true, &self.board),
-                        3 => cloned.rotate(false, &self.board),
-                        _ => return,
+                self.perform(if left {
+                    Action::ShiftLeft
+                } else {
+                    Action::ShiftRight
+                });
+            }
+        }
+
+        /// Does an action, without recording it.
+        fn perform(&mut self, action: Action) -> Outcome {
+            match action {
+                Action::ShiftLeft | Action::ShiftRight => {
+                    let moved = self.active.shift(action == Action::ShiftLeft, &self.board);
+                    if moved {
+                        self.last_was_spin = SpinType::Not;
+                    }
+                    self.move_outcome(moved, SpinType::Not)
+                }
+                Action::RotateCw | Action::RotateCcw | Action::Rotate180 => {
+                    let kick = match action {
+                        Action::Rotate180 => self.active.rotate_180(&self.board),
+                        _ => self.active.rotate(action == Action::RotateCw, &self.board),
                     };
-                    if v {
-                        return;
+                    let spin = match kick {
+                        Some(kick) => {
+                            let final_kick = action != Action::Rotate180
+                                && self.active.system.system().final_kick() == Some(kick);
+                            self.last_was_spin =
+                                self.active
+                                    .detect_spin(&self.board, self.config.spins, final_kick);
+                            self.last_was_spin
+                        }
+                        None => SpinType::Not,
+                    };
+                    self.move_outcome(kick.is_some(), spin)
+                }
+                Action::SoftDrop => {
+                    if self.active.soft_drop(&self.board) {
+                        self.last_was_spin = SpinType::Not;
+                        self.score += self.scoring.soft_drop(1, self.level);
+                        self.check_lowest();
+                        Outcome::Moved
+                    } else {
+                        self.lock();
+                        Outcome::Locked
                     }
                 }
-                self.lock();
+                Action::HardDrop => {
+                    let mut rows = 0;
+                    while self.active.soft_drop(&self.board) {
+                        rows += 1;
+                    }
+                    if rows > 0 {
+                        self.last_was_spin = SpinType::Not;
+                    }
+                    self.score += self.scoring.hard_drop(rows, self.level);
+                    self.lock();
+                    Outcome::Locked
+                }
+                Action::Hold => {
+                    let held = self.active.tetromino;
+                    if let (Some(tetromino), true) = self.held {
+                        self.held = (Some(held), false);
+                        self.events.push(GameEvent::Hold(held));
+                        self.spawn(tetromino);
+                    } else if self.held.0.is_none() {
+                        self.held = (Some(held), false);
+                        self.events.push(GameEvent::Hold(held));
+                        let next = self.next_piece();
+                        self.spawn(next);
+                    } else {
+                        return Outcome::Blocked;
+                    }
+                    Outcome::Moved
+                }
+            }
+        }
+
+        /// The outcome of a shift or rotation, once the lock reset policy has
+        /// been applied.
+        fn move_outcome(&mut self, moved: bool, spin: SpinType) -> Outcome {
+            if self.after_move(moved) {
+                Outcome::Locked
+            } else if !moved {
+                Outcome::Blocked
+            } else if spin != SpinType::Not {
+                Outcome::Spin(spin)
+            } else {
+                Outcome::Moved
+            }
+        }
+
+        /// Return the next piece in the queue and pull a new piece
+        /// from the randomizer to replace it
+        fn next_piece(&mut self) -> Tetromino {
+            let popped = self.queue.pop_front();
+            self.queue.push_back(self.randomizer.next_piece());
+            popped.unwrap()
+        }
+
+        /// Brings a new piece into play, with a fresh lock delay.
+        fn spawn(&mut self, tetromino: Tetromino) {
+            self.active = ActivePiece::new(tetromino, &self.board, self.config.rotation);
+            self.delay_count = 0;
+            self.resets_left = MOVE_RESET_LIMIT;
+            self.lowest_row = self.active.origin.0;
+            self.last_was_spin = SpinType::Not;
+            self.finesse.presses = 0;
+            self.events.push(GameEvent::PieceSpawned(tetromino));
+            if self.blocked(&self.active) {
+                self.game_over(GameOverReason::BlockOut);
+            }
+        }
+
+        /// If anything on the board is in the way of `piece`.
+        fn blocked(&self, piece: &ActivePiece) -> bool {
+            piece
+                .get_squares()
+                .iter()
+                .any(|&(row, col)| !self.board.is_empty_at(row, col))
+        }
+
+        /// Ends the game, if it hasn't already ended. In modes that can't be
+        /// topped out, topping out empties the board instead.
+        fn game_over(&mut self, reason: GameOverReason) {
+            let topped_out = matches!(
+                reason,
+                GameOverReason::LockOut | GameOverReason::BlockOut | GameOverReason::TopOut
+            );
+            if topped_out && !self.config.mode.mode().tops_out() {
+                let (width, visible, buffer) = (
+                    self.board.width(),
+                    self.board.visible(),
+                    self.board.buffer(),
+                );
+                self.board = Board::new(width, visible, buffer);
+            } else if !self.is_game_over {
+                self.is_game_over = true;
+                self.events.push(GameEvent::GameOver { reason });
+            }
+        }
+
+        /// Ends the game if its mode says it's over.
+        fn check_ending(&mut self) {
+            if let Some(reason) = self.config.mode.mode().ending(self) {
+                self.game_over(reason);
             }
         }
 
         /// Locks the active piece immediately in place.
         fn lock(&mut self) {
+            self.judge_finesse();
             // Locking the piece onto the board.
+            let mut locked_out = false;
             for (row, col) in self.active.get_squares() {
                 // Updating the game over state if we're locking above 20.
-                if !self.is_game_over {
-                    self.is_game_over = row <= 20
-                };
+                locked_out |= row <= self.board.buffer() as i32;
                 self.board[row as usize][col as usize] = self.active.tetromino as u8;
             }
+            self.stats.pieces += 1;
+            self.events.push(GameEvent::PieceLocked {
+                tetromino: self.active.tetromino,
+                spin: self.last_was_spin,
+            });
+            if locked_out {
+                self.game_over(GameOverReason::LockOut);
+            }
+            // Attempts to clear the board.
+            self.did_tetris = false;
+            let clear = self.try_clear();
+            if clear.lines > 0 {
+                // The attack cancels garbage on the way in before any of it
+                // is sent out.
+                let attack = self.config.attack.table().attack(&clear);
+                let cancelled = self.garbage.cancel(attack);
+                if cancelled > 0 {
+                    self.events.push(GameEvent::GarbageCancelled(cancelled));
+                }
+                let sent = attack - cancelled;
+                self.stats.attack += sent;
+                if sent > 0 {
+                    self.events.push(GameEvent::Attack(sent));
+                }
+            } else {
+                self.rise_garbage();
+            }
+            self.check_ending();
             // Updating the active piece.
-            self.active = ActivePiece::new(self.next_piece());
+            let next = self.next_piece();
+            self.spawn(next);
             // Allowing the held piece to be usable (if not already).
             self.held = (self.held.0, true);
-            // Attempts to clear the board.
-            self.did_tetris = false;
-            self.try_clear();
         }
 
-        /// Erase filled rows and move rows above down; as well as update the score to match.
-        fn try_clear(&mut self) {
+        /// Pushes the stack up with every line of pending garbage. Anything
+        /// pushed off the top of the board tops the player out.
+        fn rise_garbage(&mut self) {
+            let holes = self.garbage.take();
+            if holes.is_empty() {
+                return;
+            }
+            let height = self.board.height();
+            let rows = holes.len().min(height);
+            let topped_out = (0..rows).any(|row| self.board[row].iter().any(|&cell| cell != 0));
+            for row in 0..height - rows {
+                self.board[row] = self.board[row + rows].clone();
+            }
+            // The garbage sent first ends up on top.
+            for (i, &hole) in holes.iter().take(rows).enumerate() {
+                let row = &mut self.board[height - rows + i];
+                row.iter_mut().for_each(|cell| *cell = GARBAGE_CELL);
+                row[hole] = 0;
+            }
+            self.events.push(GameEvent::GarbageRose(rows as u32));
+            if topped_out {
+                self.game_over(GameOverReason::TopOut);
+            }
+        }
+
+        /// Erase filled rows and move rows above down; as well as update the
+        /// score to match. Returns what was cleared.
+        fn try_clear(&mut self) -> Clear {
             let mut l_count = 0;
-            for row in (0..MAX_ROW).rev() {
+            for row in (0..self.board.height()).rev() {
                 loop {
                     let is_solid = self.board[row].iter().all(|&itm| itm != 0);
                     if is_solid {
                         l_count += 1;
                         self.board[row].iter_mut().for_each(|x| *x = 0);
                         for sub_row in (0..row).rev() {
-                            for col in 0..MAX_COL {
+                            for col in 0..self.board.width() {
                                 self.board[sub_row + 1][col] = self.board[sub_row][col];
                             }
                         }
@@ -723,49 +1243,67 @@ pub mod tetris {
                     }
                 }
             }
+            let combo = if l_count > 0 {
+                self.combo_count += 1;
+                self.combo_count as u32
+            } else {
+                self.combo_count = -1;
+                0
+            };
+            let difficult = l_count == 4 || self.last_was_spin != SpinType::Not;
+            let b2b = l_count > 0 && difficult && self.b2b_chain.is_some();
+            let clear = Clear {
+                lines: l_count,
+                spin: self.last_was_spin,
+                b2b,
+                b2b_chain: match self.b2b_chain {
+                    Some(chain) if b2b => chain + 1,
+                    _ => 0,
+                },
+                combo,
+                perfect_clear: l_count > 0 && self.board.is_clear(),
+                level: self.level,
+                total_lines: self.lines,
+            };
             // Adding up our score.
+            self.score += self.scoring.clear(&clear);
+            self.stats.record(&clear);
+            if l_count > 0 {
+                self.events.push(GameEvent::LinesCleared {
+                    count: l_count,
+                    spin: clear.spin,
+                    b2b: clear.b2b,
+                    combo,
+                });
+                if clear.perfect_clear {
+                    self.events.push(GameEvent::PerfectClear {
+                        lines: l_count,
+                        b2b: clear.b2b,
+                    });
+                }
+                // Difficult clears build up the chain, and any other clear
+                // breaks it.
+                self.b2b_chain = match self.b2b_chain {
+                    _ if !difficult => None,
+                    Some(chain) => Some(chain + 1),
+                    None => Some(0),
+                };
+            }
             self.lines += l_count;
             if l_count == 4 {
                 self.did_tetris = true;
             }
-            let lvl = self.lines / 10;
             if !self.manually_set_level {
-                self.level = if lvl < 13 { lvl + 1 } else { 15 };
-            }
-            self.score += self.level
-                * match l_count {
-                    1 => match self.last_was_spin {
-                        SpinType::Mini => 200,
-                        SpinType::Full => 800,
-                        _ => 100,
-                    },
-                    2 => match self.last_was_spin {
-                        SpinType::Mini => 400,
-                        SpinType::Full => 1200,
-                        _ => 300,
-                    },
-                    3 => match self.last_was_spin {
-                        SpinType::Full => 1600,
-                        _ => 500,
-                    },
-                    4 => 800,
-                    _ => match self.last_was_spin {
-                        SpinType::Mini => 100,
-                        SpinType::Full => 400,
-                        _ => 0,
-                    },
-                };
-
-            if l_count > 0 {
-                self.combo_count += 1;
-                if self.combo_count > 0 {
-                    self.score += 50 * self.combo_count as u32 * self.level;
+                if let Some(level) = self.config.mode.mode().level(self.lines) {
+                    if level > self.level {
+                        self.events.push(GameEvent::LevelUp(level));
+                    }
+                    self.level = level;
                 }
-            } else {
-                self.combo_count = -1;
             }
 
             self.last_was_spin = SpinType::Not;
+            clear
         }
     }
     impl Display for Tetris {
@@ -773,36 +1311,26 @@ pub mod tetris {
             // Render the "Held" Area.
             let held_str = self.held.0.map_or(String::new(), |h| h.to_string());
             let mut held_lines = held_str.lines();
-            // Rendering the gameboard area.
-            let mut board_render = Vec::with_capacity(20);
-            for r in 0..20 {
-                let row_str: String = self.board[r + 20]
-                    .into_iter()
-                    .map(|x| if x == 0 { " ." } else { "[]" })
-                    .collect();
-                board_render.push(row_str);
-            }
-            // Rendering the piece.
-            let mut ghost = self.active;
-            while ghost.soft_drop(&self.board) {}
-            let (g_squares, a_squares) = (ghost.get_squares(), self.active.get_squares());
-            for i in 0..4 {
-                let ((g_r, g_c), (a_r, a_c)) = (g_squares[i], a_squares[i]);
-                let (g_r, g_c, a_r, a_c) = (g_r as usize, g_c as usize, a_r as usize, a_c as usize);
-                // The ghost piece first.
-                if g_r >= 20 {
-                    board_render[g_r - 20].replace_range(2 * g_c..2 * (g_c + 1), " X");
-                }
-                // Then the active piece.
-                if a_r >= 20 {
-                    board_render[a_r - 20].replace_range(2 * a_c..2 * (a_c + 1), "[]");
-                }
-            }
+            // Rendering the gameboard area, along with the active and ghost pieces.
+            let width = self.board.width();
+            let board_render: Vec<String> = self
+                .get_state()
+                .visible_rows()
+                .map(|row| {
+                    row.iter()
+                        .map(|&x| match x {
+                            0 => " .",
+                            8 => " X",
+                            _ => "[]",
+                        })
+                        .collect()
+                })
+                .collect();
             // Rendering the "Queue" Area.
             let mut queue: VecDeque<String> =
                 self.queue.iter().rev().map(|t| t.to_string()).collect();
             // Top of the Tetris Game.
-            writeln!(f, "{:>7}{:>34}", "HELD", "NEXT")?;
+            writeln!(f, "{:>7}{:>w$}", "HELD", "NEXT", w = 2 * width + 14)?;
             let score_info = format!(
                 "{:10}\n{:->9}\n{:<10}\n\n{:10}\n{:->9}\n{:<10}\n\n{:10}\n{:->9}\n{:<10}",
                 "SCORE", "", self.score, "LEVEL", "", self.level, "LINES", "", self.lines
@@ -831,8 +1359,8 @@ pub mod tetris {
                 }
             }
             // Bottom of the board.
-            writeln!(f, "{:>12}{:=>20}!>", "<!", "")?;
-            writeln!(f, "{:>32}", "\\/".repeat(10))?;
+            writeln!(f, "{:>12}{:=>w$}!>", "<!", "", w = 2 * width)?;
+            writeln!(f, "{:>w$}", "\\/".repeat(width), w = 2 * width + 12)?;
             Ok(())
         }
     }
@@ -0,0 +1,109 @@
+use super::{Clear, SpinType};
+use serde::{Deserialize, Serialize};
+use strum_macros::{Display, EnumIter, EnumString};
+
+/// An attack table decides how many lines of garbage a clear sends to an
+/// opponent.
+pub trait AttackTable {
+    /// The lines of garbage a clear is worth, before any of them go towards
+    /// cancelling garbage on the way in.
+    fn attack(&self, clear: &Clear) -> u32;
+}
+
+/// The attack tables that ship with the engine.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Default,
+    EnumIter,
+    EnumString,
+    Display,
+    Serialize,
+    Deserialize,
+)]
+#[strum(serialize_all = "lowercase")]
+pub enum AttackKind {
+    /// The Guideline's attack, as in Tetris 99 and Puyo Puyo Tetris.
+    #[default]
+    Guideline,
+    /// Tetr.io's attack, with back-to-back chains that grow stronger and
+    /// combos that multiply.
+    Tetrio,
+}
+impl AttackKind {
+    /// The attack table this kind describes.
+    pub fn table(self) -> &'static dyn AttackTable {
+        match self {
+            AttackKind::Guideline => &GuidelineAttack,
+            AttackKind::Tetrio => &TetrioAttack,
+        }
+    }
+}
+
+/// The lines sent for a clear before any bonuses, which both tables share.
+fn base_attack(lines: u32, spin: SpinType) -> u32 {
+    match (lines, spin) {
+        (0, _) => 0,
+        (lines, SpinType::Full(_)) => 2 * lines,
+        (lines, SpinType::Mini(_)) => lines - 1,
+        (4, SpinType::Not) => 4,
+        (lines, SpinType::Not) => lines - 1,
+    }
+}
+
+/// The extra lines the Guideline sends for each combo.
+const GUIDELINE_COMBO: [u32; 12] = [0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 4, 5];
+
+/// The lines a perfect clear adds to its attack.
+const PERFECT_CLEAR_ATTACK: u32 = 10;
+
+/// [Guideline attack](https://tetris.wiki/Garbage#Scoring_table). Back-to-backs
+/// add a line, and combos add lines from a table.
+pub struct GuidelineAttack;
+impl AttackTable for GuidelineAttack {
+    fn attack(&self, clear: &Clear) -> u32 {
+        if clear.lines == 0 {
+            return 0;
+        }
+        let combo = GUIDELINE_COMBO[(clear.combo as usize).min(GUIDELINE_COMBO.len() - 1)];
+        let perfect_clear = if clear.perfect_clear {
+            PERFECT_CLEAR_ATTACK
+        } else {
+            0
+        };
+        base_attack(clear.lines, clear.spin) + clear.b2b as u32 + combo + perfect_clear
+    }
+}
+
+/// [Tetr.io attack](https://tetris.wiki/TETR.IO#Garbage). Back-to-backs add a
+/// line at first, and more as the chain gets longer, while every clear in a
+/// combo is worth a quarter more than it would be on its own.
+pub struct TetrioAttack;
+impl AttackTable for TetrioAttack {
+    fn attack(&self, clear: &Clear) -> u32 {
+        if clear.lines == 0 {
+            return 0;
+        }
+        let mut attack = base_attack(clear.lines, clear.spin) as f64;
+        if clear.b2b {
+            attack += (1.0 + (clear.b2b_chain as f64 * 0.8).ln_1p()).floor();
+        }
+        let combo = clear.combo as f64;
+        attack = if attack > 0.0 {
+            (attack * (1.0 + 0.25 * combo)).floor()
+        } else {
+            // Clears that send nothing on their own still send a little in
+            // a long enough combo.
+            (combo * 1.25).ln_1p().floor()
+        };
+        let perfect_clear = if clear.perfect_clear {
+            PERFECT_CLEAR_ATTACK
+        } else {
+            0
+        };
+        attack as u32 + perfect_clear
+    }
+}
@@ -0,0 +1,158 @@
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::ops::{Index, IndexMut};
+
+/// The fewest hidden rows a board can have, so that pieces spawning at the
+/// top of the visible rows have room to poke out above them.
+pub const MIN_BUFFER: usize = 2;
+
+/// Why a list of rows couldn't be made into a board.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BoardError {
+    /// There were no rows at all.
+    Empty,
+    /// The rows weren't all the same width.
+    Ragged,
+    /// The rows were less than 4 cells wide.
+    TooNarrow,
+    /// There weren't any visible rows, or enough rows left over above them
+    /// for the buffer.
+    TooShort,
+}
+impl Display for BoardError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            BoardError::Empty => "the board has no rows",
+            BoardError::Ragged => "the board's rows aren't all the same width",
+            BoardError::TooNarrow => "the board is less than 4 wide",
+            BoardError::TooShort => "the board doesn't have room for its visible rows and buffer",
+        })
+    }
+}
+impl Error for BoardError {}
+
+/// The playfield. It's made up of `visible` rows that the player can see, and
+/// `buffer` hidden rows above them that pieces spawn into and can be pushed up
+/// through. Row `0` is the top of the hidden buffer, and the last row is the
+/// bottom of the board. Each cell is `0` when empty, or the number of the
+/// tetromino that filled it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "RawBoard")]
+pub struct Board {
+    visible: usize,
+    cells: Vec<Vec<u8>>,
+}
+
+/// A board as it's written out, before it's been checked.
+#[derive(Deserialize)]
+struct RawBoard {
+    visible: usize,
+    cells: Vec<Vec<u8>>,
+}
+impl TryFrom<RawBoard> for Board {
+    type Error = BoardError;
+
+    fn try_from(raw: RawBoard) -> Result<Self, Self::Error> {
+        Board::from_rows(raw.cells, raw.visible)
+    }
+}
+impl Board {
+    /// Creates an empty board. Boards must be at least 4 cells wide, with at
+    /// least [`MIN_BUFFER`] hidden rows, so that every piece has room to
+    /// spawn.
+    pub fn new(width: usize, visible: usize, buffer: usize) -> Self {
+        if let Err(e) = Board::check_size(width, visible, buffer) {
+            panic!("{}", e);
+        }
+        Board {
+            visible,
+            cells: vec![vec![0; width]; visible + buffer],
+        }
+    }
+
+    /// Checks that [`Board::new`] would accept a board of this size.
+    pub fn check_size(width: usize, visible: usize, buffer: usize) -> Result<(), BoardError> {
+        if width < 4 {
+            return Err(BoardError::TooNarrow);
+        }
+        if visible == 0 || buffer < MIN_BUFFER {
+            return Err(BoardError::TooShort);
+        }
+        Ok(())
+    }
+
+    /// Creates a board from a list of rows, the last `visible` of which are
+    /// shown to the player. The rows have to make a board that
+    /// [`Board::new`] would accept.
+    pub fn from_rows(rows: Vec<Vec<u8>>, visible: usize) -> Result<Self, BoardError> {
+        let width = rows.first().ok_or(BoardError::Empty)?.len();
+        if rows.iter().any(|row| row.len() != width) {
+            return Err(BoardError::Ragged);
+        }
+        Board::check_size(width, visible, rows.len().saturating_sub(visible))?;
+        Ok(Board {
+            visible,
+            cells: rows,
+        })
+    }
+
+    /// The number of columns.
+    pub fn width(&self) -> usize {
+        self.cells[0].len()
+    }
+
+    /// The total number of rows, hidden buffer included.
+    pub fn height(&self) -> usize {
+        self.cells.len()
+    }
+
+    /// The number of rows that are shown to the player.
+    pub fn visible(&self) -> usize {
+        self.visible
+    }
+
+    /// The number of hidden rows above the visible ones. This is also the
+    /// index of the top visible row.
+    pub fn buffer(&self) -> usize {
+        self.height() - self.visible
+    }
+
+    /// The rows that are shown to the player, from top to bottom.
+    pub fn visible_rows(&self) -> impl Iterator<Item = &Vec<u8>> {
+        self.cells.iter().skip(self.buffer())
+    }
+
+    /// If a (signed) coordinate lies on the board.
+    pub fn in_range(&self, row: i32, col: i32) -> bool {
+        row >= 0 && col >= 0 && (row as usize) < self.height() && (col as usize) < self.width()
+    }
+
+    /// If there's nothing at all on the board.
+    pub fn is_clear(&self) -> bool {
+        self.cells.iter().flatten().all(|&cell| cell == 0)
+    }
+
+    /// If a (signed) coordinate is on the board, and nothing is in it.
+    pub fn is_empty_at(&self, row: i32, col: i32) -> bool {
+        self.in_range(row, col) && self.cells[row as usize][col as usize] == 0
+    }
+}
+impl Default for Board {
+    /// The Guideline board; 10 wide and 20 tall, with another 20 hidden rows.
+    fn default() -> Self {
+        Board::new(10, 20, 20)
+    }
+}
+impl Index<usize> for Board {
+    type Output = Vec<u8>;
+
+    fn index(&self, row: usize) -> &Self::Output {
+        &self.cells[row]
+    }
+}
+impl IndexMut<usize> for Board {
+    fn index_mut(&mut self, row: usize) -> &mut Self::Output {
+        &mut self.cells[row]
+    }
+}
@@ -0,0 +1,298 @@
+use super::{movegen::search, ActivePiece, Board, Config, Placement, SpinType, Tetris, Tetromino};
+
+/// How many of the best placements at each step of the lookahead are looked
+/// into any further.
+const BEAM_WIDTH: usize = 6;
+
+/// The features of a board that the bot judges it by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Features {
+    /// The heights of every column, added up.
+    pub height: u32,
+    /// Empty cells with something above them in the same column.
+    pub holes: u32,
+    /// The differences in height between neighbouring columns, added up.
+    pub bumpiness: u32,
+    /// The changes between filled and empty cells along each row, counting
+    /// the walls as filled. Only rows from the top of the stack down count.
+    pub row_transitions: u32,
+    /// The changes between filled and empty cells down each column, counting
+    /// the floor as filled.
+    pub column_transitions: u32,
+    /// How far every well sits below the columns either side of it, added up.
+    pub well_depth: u32,
+    /// Slots a T could spin into for a T-spin double.
+    pub t_slots: u32,
+}
+impl Features {
+    /// Measures `board`.
+    pub fn of(board: &Board) -> Self {
+        let (width, height) = (board.width(), board.height());
+        let filled = |row: usize, col: usize| board[row][col] != 0;
+        let heights: Vec<usize> = (0..width)
+            .map(|col| {
+                (0..height)
+                    .find(|&row| filled(row, col))
+                    .map_or(0, |row| height - row)
+            })
+            .collect();
+        let top = height - heights.iter().copied().max().unwrap_or(0);
+        let mut features = Features {
+            height: heights.iter().sum::<usize>() as u32,
+            bumpiness: heights
+                .windows(2)
+                .map(|pair| pair[0].abs_diff(pair[1]) as u32)
+                .sum(),
+            ..Features::default()
+        };
+        for (col, &column_height) in heights.iter().enumerate() {
+            features.holes += (height - column_height..height)
+                .filter(|&row| !filled(row, col))
+                .count() as u32;
+            // The floor is filled, and so is anything off the top of the stack.
+            let mut above = false;
+            for row in top..height {
+                features.column_transitions += (filled(row, col) != above) as u32;
+                above = filled(row, col);
+            }
+            features.column_transitions += !above as u32;
+            let left = col.checked_sub(1).map_or(usize::MAX, |col| heights[col]);
+            let right = heights.get(col + 1).copied().unwrap_or(usize::MAX);
+            let sides = left.min(right);
+            if sides != usize::MAX && sides > column_height {
+                features.well_depth += (sides - column_height) as u32;
+            }
+        }
+        for row in top..height {
+            let mut left = true;
+            for col in 0..width {
+                features.row_transitions += (filled(row, col) != left) as u32;
+                left = filled(row, col);
+            }
+            features.row_transitions += !left as u32;
+        }
+        // A T-spin double slot: the T's three cells along one row, with its
+        // nub below them, both sides of the nub filled, and an overhang on
+        // one side above.
+        for row in top.max(1)..height - 1 {
+            for col in 1..width - 1 {
+                let open = !filled(row, col - 1)
+                    && !filled(row, col)
+                    && !filled(row, col + 1)
+                    && !filled(row + 1, col)
+                    && !filled(row - 1, col);
+                let walls = filled(row + 1, col - 1) && filled(row + 1, col + 1);
+                let overhang = filled(row - 1, col - 1) || filled(row - 1, col + 1);
+                features.t_slots += (open && walls && overhang) as u32;
+            }
+        }
+        features
+    }
+}
+
+/// How much each feature of a board, and each kind of clear, is worth to the
+/// bot. Features that make a board worse should have negative weights.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Weights {
+    pub height: f64,
+    pub holes: f64,
+    pub bumpiness: f64,
+    pub row_transitions: f64,
+    pub column_transitions: f64,
+    pub well_depth: f64,
+    pub t_slots: f64,
+    /// What clearing 0 to 4 lines without a spin is worth.
+    pub clears: [f64; 5],
+    /// What a full spin clearing 0 to 3 lines is worth.
+    pub spins: [f64; 4],
+}
+impl Default for Weights {
+    fn default() -> Self {
+        Weights {
+            height: -0.4,
+            holes: -4.0,
+            bumpiness: -0.3,
+            row_transitions: -0.4,
+            column_transitions: -0.6,
+            well_depth: -0.3,
+            t_slots: 1.5,
+            clears: [0.0, -1.0, -0.5, 1.0, 6.0],
+            spins: [0.0, 3.0, 8.0, 10.0],
+        }
+    }
+}
+impl Weights {
+    /// What `board` is worth.
+    pub fn evaluate(&self, board: &Board) -> f64 {
+        let features = Features::of(board);
+        self.height * features.height as f64
+            + self.holes * features.holes as f64
+            + self.bumpiness * features.bumpiness as f64
+            + self.row_transitions * features.row_transitions as f64
+            + self.column_transitions * features.column_transitions as f64
+            + self.well_depth * features.well_depth as f64
+            + self.t_slots * features.t_slots as f64
+    }
+
+    /// What clearing `lines` lines with `spin` is worth.
+    pub fn reward(&self, lines: u32, spin: SpinType) -> f64 {
+        match spin {
+            SpinType::Full(_) => self.spins[(lines as usize).min(3)],
+            _ => self.clears[(lines as usize).min(4)],
+        }
+    }
+}
+
+/// A player that picks its placements by how good the board they leave
+/// looks, looking ahead through the queue.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bot {
+    pub weights: Weights,
+    /// How many pieces of the queue to look at beyond the active one.
+    pub lookahead: usize,
+}
+impl Default for Bot {
+    fn default() -> Self {
+        Bot::new(1)
+    }
+}
+
+/// What's left of a game partway through the lookahead.
+struct Future {
+    board: Board,
+    held: Option<Tetromino>,
+    /// The piece of the queue that comes out next.
+    next: usize,
+}
+
+impl Bot {
+    /// A bot with the default weights, looking `lookahead` pieces ahead.
+    pub fn new(lookahead: usize) -> Self {
+        Bot {
+            weights: Weights::default(),
+            lookahead,
+        }
+    }
+
+    /// The placement the bot would make, if it can make any.
+    pub fn choose(&self, tetris: &Tetris) -> Option<Placement> {
+        let queue: Vec<Tetromino> = tetris.queue.iter().copied().collect();
+        let depth = self.lookahead.min(queue.len());
+        let mut best: Option<(f64, Placement)> = None;
+        let children = self.expand(
+            &tetris.board,
+            tetris.active.tetromino,
+            tetris.placements(),
+            tetris.held.0,
+            0,
+        );
+        for (placement, value, future) in children {
+            let value = value + self.best(&tetris.config, &queue, future, depth);
+            if best.as_ref().is_none_or(|(best, _)| value > *best) {
+                best = Some((value, placement));
+            }
+        }
+        best.map(|(_, placement)| placement)
+    }
+
+    /// Chooses a placement and makes all of its inputs.
+    pub fn play(&self, tetris: &mut Tetris) -> Option<Placement> {
+        let placement = self.choose(tetris)?;
+        for &action in &placement.inputs {
+            tetris.apply(action);
+        }
+        Some(placement)
+    }
+
+    /// The best value that can be had from `future` over the next `depth`
+    /// pieces.
+    fn best(&self, config: &Config, queue: &[Tetromino], future: Future, depth: usize) -> f64 {
+        if depth == 0 || future.next >= queue.len() {
+            return self.weights.evaluate(&future.board);
+        }
+        let board = &future.board;
+        let piece = queue[future.next];
+        let active = ActivePiece::new(piece, board, config.rotation);
+        if !active.can_move(0, 0, board) {
+            return f64::NEG_INFINITY;
+        }
+        let mut placements = search(board, config, active, SpinType::Not, false);
+        // Holding swaps in the held piece, or the one after if there isn't
+        // one.
+        let from_hold = future.held.or(queue.get(future.next + 1).copied());
+        if let Some(from_hold) = from_hold.filter(|&other| other != piece) {
+            let held = ActivePiece::new(from_hold, board, config.rotation);
+            if held.can_move(0, 0, board) {
+                placements.extend(search(board, config, held, SpinType::Not, true));
+            }
+        }
+        self.expand(board, piece, placements, future.held, future.next + 1)
+            .into_iter()
+            .map(|(_, value, future)| value + self.best(config, queue, future, depth - 1))
+            .fold(f64::NEG_INFINITY, f64::max)
+    }
+
+    /// Makes each placement of `active`, or the piece swapped in for it, on
+    /// `board`, and keeps the ones that look best, along with what they're
+    /// worth and the future they leave. `next` is the piece of the queue
+    /// after the active one.
+    fn expand(
+        &self,
+        board: &Board,
+        active: Tetromino,
+        placements: Vec<Placement>,
+        held: Option<Tetromino>,
+        next: usize,
+    ) -> Vec<(Placement, f64, Future)> {
+        let mut children: Vec<(Placement, f64, Future, f64)> = placements
+            .into_iter()
+            // Locking any part of a piece out of sight ends the game.
+            .filter(|placement| placement.cells.iter().all(|&(row, _)| row > board.buffer()))
+            .map(|placement| {
+                let (board, lines) = place(board, &placement);
+                let value = self.weights.reward(lines, placement.spin);
+                let future = match (placement.hold, held) {
+                    (false, _) => Future { board, held, next },
+                    (true, Some(_)) => Future {
+                        board,
+                        held: Some(active),
+                        next,
+                    },
+                    (true, None) => Future {
+                        board,
+                        held: Some(active),
+                        next: next + 1,
+                    },
+                };
+                let guess = value + self.weights.evaluate(&future.board);
+                (placement, value, future, guess)
+            })
+            .collect();
+        children.sort_by(|a, b| b.3.total_cmp(&a.3));
+        children.truncate(BEAM_WIDTH);
+        children
+            .into_iter()
+            .map(|(placement, value, future, _)| (placement, value, future))
+            .collect()
+    }
+}
+
+/// The board after locking `placement` onto `board`, and the number of lines
+/// that cleared.
+pub(super) fn place(board: &Board, placement: &Placement) -> (Board, u32) {
+    let mut board = board.clone();
+    for &(row, col) in &placement.cells {
+        board[row][col] = placement.tetromino as u8;
+    }
+    let width = board.width();
+    let mut rows: Vec<Vec<u8>> = (0..board.height())
+        .map(|row| board[row].clone())
+        .filter(|row| row.contains(&0))
+        .collect();
+    let lines = board.height() - rows.len();
+    let mut cleared = vec![vec![0; width]; lines];
+    cleared.append(&mut rows);
+    let board = Board::from_rows(cleared, board.visible())
+        .expect("clearing lines keeps the board the same size");
+    (board, lines as u32)
+}
@@ -0,0 +1,69 @@
+use super::{FinesseFault, SpinType, Tetromino};
+use serde::{Deserialize, Serialize};
+
+/// Something that happened in a game. The engine queues these up as they
+/// happen, and frontends take them with [`Tetris::take_events`] to drive
+/// anything that reacts to the game, such as the UI, stats or sound.
+///
+/// [`Tetris::take_events`]: super::Tetris::take_events
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GameEvent {
+    /// A new piece came into play.
+    PieceSpawned(Tetromino),
+    /// The active piece locked onto the board, with the spin it was put in
+    /// with, if any.
+    PieceLocked {
+        tetromino: Tetromino,
+        spin: SpinType,
+    },
+    /// Rows were cleared by the last piece to lock.
+    LinesCleared {
+        count: u32,
+        spin: SpinType,
+        /// If this clear and the one before it were both difficult; a
+        /// tetris or a spin that cleared lines.
+        b2b: bool,
+        /// The number of clears in a row before this one, without a piece
+        /// locking in between that didn't clear anything.
+        combo: u32,
+    },
+    /// The last clear emptied the board. This comes straight after the
+    /// clear's `LinesCleared`, and carries the same lines and back-to-back.
+    PerfectClear {
+        lines: u32,
+        b2b: bool,
+    },
+    /// The last clear sent lines of garbage to the opponent, after
+    /// cancelling out any garbage of its own.
+    Attack(u32),
+    /// Lines of garbage rose up from the bottom of the board.
+    GarbageRose(u32),
+    /// Clearing lines cancelled out lines of garbage before they could rise.
+    GarbageCancelled(u32),
+    /// The piece that just locked took more presses than it needed. This
+    /// comes just before its `PieceLocked`.
+    FinesseFault(FinesseFault),
+    /// A piece was put into hold.
+    Hold(Tetromino),
+    /// The level went up to the given level from clearing lines.
+    LevelUp(u32),
+    GameOver {
+        reason: GameOverReason,
+    },
+}
+
+/// The ways a game can end.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GameOverReason {
+    /// A piece locked touching the top row of the board, or above it.
+    LockOut,
+    /// A new piece spawned on top of blocks already on the board.
+    BlockOut,
+    /// Garbage pushed blocks off the top of the board.
+    TopOut,
+    /// The player reached the goal of the game mode, like clearing the lines
+    /// of a sprint.
+    Finished,
+    /// The time limit of the game mode ran out.
+    TimeUp,
+}
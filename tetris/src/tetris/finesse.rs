@@ -0,0 +1,140 @@
+use super::{Action, ActivePiece, GameEvent, Tetris, Tetromino};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashSet, VecDeque};
+
+/// How cleanly the pieces of a game have been put in place.
+///
+/// Finesse is judged against the fewest key presses that put a piece from
+/// where it spawns into the same spot, counting a tap, a held shift to the
+/// wall (DAS) and a rotation as one press each, and drops as none. Spots
+/// that can only be reached with a soft drop, like tucks and spins, aren't
+/// judged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct Finesse {
+    /// The presses made on the active piece so far.
+    pub(super) presses: u32,
+    /// The pieces that were judged.
+    pub judged: u32,
+    /// The pieces that took more presses than they needed.
+    pub faults: u32,
+    /// The last piece that did, and the frame it locked on.
+    pub last_fault: Option<(FinesseFault, u64)>,
+}
+impl Finesse {
+    /// The share of judged pieces that were put in place without a fault,
+    /// as a percentage. A game with nothing judged yet is at 100%.
+    pub fn accuracy(&self) -> f64 {
+        match self.judged {
+            0 => 100.0,
+            judged => 100.0 * (judged - self.faults) as f64 / judged as f64,
+        }
+    }
+
+    /// Counts an input towards the active piece's presses. Drops and holds
+    /// don't count.
+    pub(super) fn press(&mut self, action: Action) {
+        if !matches!(action, Action::SoftDrop | Action::HardDrop | Action::Hold) {
+            self.presses += 1;
+        }
+    }
+}
+
+/// A piece that took more presses than it needed to put in place.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FinesseFault {
+    pub tetromino: Tetromino,
+    /// The presses the piece took.
+    pub presses: u32,
+    /// The fewest presses it could have taken.
+    pub fewest: u32,
+}
+
+/// A move that costs one press.
+#[derive(Clone, Copy)]
+enum Press {
+    /// A single shift or rotation.
+    Tap(Action),
+    /// A shift held all the way to the wall, to the left if `true`.
+    Das(bool),
+}
+
+/// Every move that costs one press, bar soft dropping.
+const PRESSES: [Press; 7] = [
+    Press::Tap(Action::ShiftLeft),
+    Press::Tap(Action::ShiftRight),
+    Press::Tap(Action::RotateCw),
+    Press::Tap(Action::RotateCcw),
+    Press::Tap(Action::Rotate180),
+    Press::Das(true),
+    Press::Das(false),
+];
+
+impl Tetris {
+    /// Judges the finesse of the active piece as it locks where it is now.
+    /// This has to happen before it's written onto the board.
+    pub(super) fn judge_finesse(&mut self) {
+        let presses = self.finesse.presses;
+        let Some(fewest) = self.fewest_presses(&self.active) else {
+            return;
+        };
+        self.finesse.judged += 1;
+        if presses > fewest {
+            let fault = FinesseFault {
+                tetromino: self.active.tetromino,
+                presses,
+                fewest,
+            };
+            self.finesse.faults += 1;
+            self.finesse.last_fault = Some((fault, self.frame));
+            self.events.push(GameEvent::FinesseFault(fault));
+        }
+    }
+
+    /// The fewest presses that put `target`'s piece from its spawn into the
+    /// cells `target` fills, before hard dropping it there, if that can be
+    /// done without soft dropping.
+    fn fewest_presses(&self, target: &ActivePiece) -> Option<u32> {
+        let mut goal = target.get_squares();
+        goal.sort_unstable();
+        let start = ActivePiece::new(target.tetromino, &self.board, self.config.rotation);
+        if self.blocked(&start) {
+            return None;
+        }
+        // Every move costs the same, so a breadth-first search finds the
+        // fewest presses first.
+        let key = |piece: &ActivePiece| (piece.origin.0, piece.origin.1, piece.rotation);
+        let mut seen = HashSet::from([key(&start)]);
+        let mut frontier = VecDeque::from([(start, 0)]);
+        while let Some((piece, presses)) = frontier.pop_front() {
+            let mut landing = piece;
+            while landing.soft_drop(&self.board) {}
+            let mut cells = landing.get_squares();
+            cells.sort_unstable();
+            if cells == goal {
+                return Some(presses);
+            }
+            for press in PRESSES {
+                let mut next = piece;
+                let moved = match press {
+                    Press::Tap(Action::ShiftLeft) => next.shift(true, &self.board),
+                    Press::Tap(Action::ShiftRight) => next.shift(false, &self.board),
+                    Press::Tap(Action::RotateCw) => next.rotate(true, &self.board).is_some(),
+                    Press::Tap(Action::RotateCcw) => next.rotate(false, &self.board).is_some(),
+                    Press::Tap(Action::Rotate180) => next.rotate_180(&self.board).is_some(),
+                    Press::Tap(_) => false,
+                    Press::Das(left) => {
+                        let mut moved = false;
+                        while next.shift(left, &self.board) {
+                            moved = true;
+                        }
+                        moved
+                    }
+                };
+                if moved && seen.insert(key(&next)) {
+                    frontier.push_back((next, presses + 1));
+                }
+            }
+        }
+        None
+    }
+}
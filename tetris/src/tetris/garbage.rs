@@ -0,0 +1,62 @@
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// What garbage cells are on the board, next to the numbers of the pieces.
+pub const GARBAGE_CELL: u8 = 9;
+
+/// Garbage waiting to rise into a game, as the hole column of each line, in
+/// the order the lines were sent.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(super) struct GarbageQueue {
+    pending: VecDeque<usize>,
+    /// Picks the holes. It's kept apart from the randomizer's RNG, so that
+    /// garbage doesn't change the pieces that get dealt.
+    rng: ChaCha8Rng,
+}
+impl GarbageQueue {
+    pub(super) fn new(seed: u64) -> Self {
+        GarbageQueue {
+            pending: VecDeque::new(),
+            rng: ChaCha8Rng::seed_from_u64(seed.wrapping_add(1)),
+        }
+    }
+
+    /// The number of lines waiting to rise.
+    pub(super) fn len(&self) -> u32 {
+        self.pending.len() as u32
+    }
+
+    /// Queues up `lines` lines for a board `width` wide. Each batch gets a
+    /// new hole, and every line after the first has a `messiness` percent
+    /// chance of moving its hole somewhere else.
+    pub(super) fn push(&mut self, lines: u32, width: usize, messiness: u8) {
+        let mut hole = self.rng.gen_range(0..width);
+        for i in 0..lines {
+            if i > 0 && width > 1 && self.rng.gen_range(0..100) < messiness {
+                // Somewhere other than where it was.
+                hole = (hole + self.rng.gen_range(1..width)) % width;
+            }
+            self.pending.push_back(hole);
+        }
+    }
+
+    /// Cancels up to `lines` of the garbage that was sent first, and returns
+    /// how many lines were cancelled.
+    pub(super) fn cancel(&mut self, lines: u32) -> u32 {
+        let cancelled = self.pending.len().min(lines as usize);
+        self.pending.drain(..cancelled);
+        cancelled as u32
+    }
+
+    /// Takes every line of garbage out of the queue.
+    pub(super) fn take(&mut self) -> Vec<usize> {
+        self.pending.drain(..).collect()
+    }
+}
+impl Default for GarbageQueue {
+    fn default() -> Self {
+        GarbageQueue::new(0)
+    }
+}
@@ -0,0 +1,50 @@
+use serde::{Deserialize, Serialize};
+
+/// How held inputs repeat. Every value is counted in frames, so handling
+/// plays out the same no matter how fast the frontend polls its keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Handling {
+    /// Delayed auto shift; how long a direction has to be held before the
+    /// piece starts moving on its own.
+    pub das: u8,
+    /// Auto repeat rate; the frames between each shift once the DAS has
+    /// charged. At 0, the piece goes straight to the wall.
+    pub arr: u8,
+    /// Soft drop factor; how many times faster than gravity a held soft drop
+    /// makes the piece fall. At 0, the piece goes straight to the floor.
+    pub sdf: u8,
+}
+impl Default for Handling {
+    fn default() -> Self {
+        Handling {
+            das: 10,
+            arr: 2,
+            sdf: 20,
+        }
+    }
+}
+
+/// Which inputs are being held down, and how far along their repeating is.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub(super) struct HeldInputs {
+    pub(super) left: bool,
+    pub(super) right: bool,
+    pub(super) soft_drop: bool,
+    /// The direction that repeats, if any; the last one pressed. `true` is
+    /// left.
+    pub(super) direction: Option<bool>,
+    /// The frames that the direction has been held for, up to the DAS. It's
+    /// kept when a new piece spawns, so a charged DAS carries over to it.
+    pub(super) das_charge: u8,
+    /// The frames since the last repeated shift.
+    pub(super) arr_count: u8,
+}
+impl HeldInputs {
+    /// Starts repeating in a direction from scratch, or stops repeating.
+    pub(super) fn repeat(&mut self, direction: Option<bool>) {
+        self.direction = direction;
+        self.das_charge = 0;
+        self.arr_count = 0;
+    }
+}
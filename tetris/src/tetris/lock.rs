@@ -0,0 +1,41 @@
+use serde::{Deserialize, Serialize};
+use strum_macros::{Display, EnumIter, EnumString};
+
+/// The number of moves a piece can make on the stack under [`LockReset::Move`]
+/// before moving stops buying it more time.
+pub const MOVE_RESET_LIMIT: u8 = 15;
+
+/// The number of frames a piece can rest on the stack before it locks, at
+/// every level, unless a game is set up otherwise.
+pub const DEFAULT_LOCK_DELAY: u8 = 30;
+
+/// What restarts the lock delay of a piece that is resting on the stack.
+///
+/// Whatever the policy, a piece that falls lower than it has been before
+/// always gets a fresh lock delay.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Default,
+    EnumIter,
+    EnumString,
+    Display,
+    Serialize,
+    Deserialize,
+)]
+#[strum(serialize_all = "lowercase")]
+pub enum LockReset {
+    /// The Guideline's "move reset". Every shift or rotation restarts the
+    /// delay, up to [`MOVE_RESET_LIMIT`] times for each piece. Once those
+    /// are used up, the piece locks as soon as it touches the stack.
+    #[default]
+    Move,
+    /// The classic "step reset", where only falling restarts the delay.
+    Step,
+    /// Pieces never lock on their own, and have to be dropped. Handy for
+    /// practice.
+    Infinite,
+}
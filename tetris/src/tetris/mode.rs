@@ -0,0 +1,195 @@
+use super::{GameOverReason, Tetris};
+use serde::{Deserialize, Serialize};
+use strum_macros::{Display, EnumIter, EnumString};
+
+/// The frames in a second; the engine assumes it's run at 60 frames a second.
+pub(super) const FPS: u64 = 60;
+
+/// The lines to clear in a sprint.
+pub const SPRINT_LINES: u32 = 40;
+
+/// The frames an ultra lasts for; 2 minutes.
+pub const ULTRA_FRAMES: u64 = 2 * 60 * FPS;
+
+/// The lines that finish a marathon, at the end of level 15.
+pub const MARATHON_LINES: u32 = 150;
+
+/// A game mode decides what a game is played for: how it ends, how the level
+/// goes up, and what's worth showing the player while they play.
+pub trait GameMode {
+    /// The name of the mode, for menus.
+    fn name(&self) -> &'static str;
+
+    /// The level a game is on after clearing `lines` lines, or `None` if the
+    /// level never changes by itself.
+    fn level(&self, lines: u32) -> Option<u32> {
+        Some((lines / 10 + 1).min(15))
+    }
+
+    /// If the game has come to its end, the reason why. This is checked every
+    /// frame and after every piece locks.
+    fn ending(&self, _game: &Tetris) -> Option<GameOverReason> {
+        None
+    }
+
+    /// If topping out ends the game. Otherwise the board is emptied and play
+    /// carries on.
+    fn tops_out(&self) -> bool {
+        true
+    }
+
+    /// The two fields shown beside the board, as titles and values.
+    fn hud(&self, game: &Tetris) -> [(&'static str, String); 2] {
+        [
+            ("LEVEL", game.level.to_string()),
+            ("LINES", game.lines.to_string()),
+        ]
+    }
+}
+
+/// The game modes that ship with the engine.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Default,
+    EnumIter,
+    EnumString,
+    Display,
+    Serialize,
+    Deserialize,
+)]
+#[strum(serialize_all = "lowercase")]
+pub enum GameModeKind {
+    /// Play until topping out, with the level going up every 10 lines.
+    #[default]
+    Endless,
+    /// Clear 150 lines, through all 15 levels.
+    Marathon,
+    /// Clear 40 lines as fast as possible.
+    Sprint,
+    /// Score as much as possible in 2 minutes.
+    Ultra,
+    /// Play forever, without being able to top out.
+    Zen,
+}
+impl GameModeKind {
+    /// The game mode this kind describes.
+    pub fn mode(self) -> &'static dyn GameMode {
+        match self {
+            GameModeKind::Endless => &Endless,
+            GameModeKind::Marathon => &Marathon,
+            GameModeKind::Sprint => &Sprint,
+            GameModeKind::Ultra => &Ultra,
+            GameModeKind::Zen => &Zen,
+        }
+    }
+}
+
+/// Formats a number of frames as a time, like `1:23.45`.
+pub fn format_time(frames: u64) -> String {
+    let hundredths = frames * 100 / FPS;
+    format!(
+        "{}:{:02}.{:02}",
+        hundredths / 6000,
+        hundredths / 100 % 60,
+        hundredths % 100
+    )
+}
+
+/// The original mode, which goes on until the player tops out. The level goes
+/// up every 10 lines, skipping from 13 straight to 15.
+pub struct Endless;
+impl GameMode for Endless {
+    fn name(&self) -> &'static str {
+        "Endless"
+    }
+
+    fn level(&self, lines: u32) -> Option<u32> {
+        let level = lines / 10;
+        Some(if level < 13 { level + 1 } else { 15 })
+    }
+}
+
+/// The Guideline's marathon, which ends after [`MARATHON_LINES`] lines.
+pub struct Marathon;
+impl GameMode for Marathon {
+    fn name(&self) -> &'static str {
+        "Marathon"
+    }
+
+    fn ending(&self, game: &Tetris) -> Option<GameOverReason> {
+        (game.lines >= MARATHON_LINES).then_some(GameOverReason::Finished)
+    }
+
+    fn hud(&self, game: &Tetris) -> [(&'static str, String); 2] {
+        [
+            ("LEVEL", game.level.to_string()),
+            ("LINES", format!("{}/{}", game.lines, MARATHON_LINES)),
+        ]
+    }
+}
+
+/// A race to clear [`SPRINT_LINES`] lines, on a level that never goes up.
+pub struct Sprint;
+impl GameMode for Sprint {
+    fn name(&self) -> &'static str {
+        "Sprint 40L"
+    }
+
+    fn level(&self, _lines: u32) -> Option<u32> {
+        None
+    }
+
+    fn ending(&self, game: &Tetris) -> Option<GameOverReason> {
+        (game.lines >= SPRINT_LINES).then_some(GameOverReason::Finished)
+    }
+
+    fn hud(&self, game: &Tetris) -> [(&'static str, String); 2] {
+        [
+            ("TIME", format_time(game.get_frame())),
+            ("LINES", format!("{}/{}", game.lines, SPRINT_LINES)),
+        ]
+    }
+}
+
+/// As many points as possible before [`ULTRA_FRAMES`] run out, on a level that
+/// never goes up.
+pub struct Ultra;
+impl GameMode for Ultra {
+    fn name(&self) -> &'static str {
+        "Ultra 2:00"
+    }
+
+    fn level(&self, _lines: u32) -> Option<u32> {
+        None
+    }
+
+    fn ending(&self, game: &Tetris) -> Option<GameOverReason> {
+        (game.get_frame() >= ULTRA_FRAMES).then_some(GameOverReason::TimeUp)
+    }
+
+    fn hud(&self, game: &Tetris) -> [(&'static str, String); 2] {
+        [
+            (
+                "TIME",
+                format_time(ULTRA_FRAMES.saturating_sub(game.get_frame())),
+            ),
+            ("LINES", game.lines.to_string()),
+        ]
+    }
+}
+
+/// Relaxed play that never ends. Topping out just empties the board.
+pub struct Zen;
+impl GameMode for Zen {
+    fn name(&self) -> &'static str {
+        "Zen"
+    }
+
+    fn tops_out(&self) -> bool {
+        false
+    }
+}
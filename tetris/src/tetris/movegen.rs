@@ -0,0 +1,235 @@
+use super::{Action, ActivePiece, Board, Config, SpinType, State, Tetris, Tetromino};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+/// A place a piece can lock, and the inputs that get it there.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Placement {
+    pub tetromino: Tetromino,
+    pub rotation: State,
+    /// The cells the piece locks into, as `(row, col)` from the top left of
+    /// the board, buffer included, sorted.
+    pub cells: [(usize, usize); 4],
+    /// If the piece is swapped in from hold first.
+    pub hold: bool,
+    /// The spin the piece locks with.
+    pub spin: SpinType,
+    /// Every input that puts the piece there, through [`Tetris::apply`],
+    /// ending with the hard drop that locks it. It starts with a hold if the
+    /// piece comes out of hold.
+    pub inputs: Vec<Action>,
+}
+
+/// Everything a piece can do before it's dropped, bar holding.
+const MOVES: [Action; 6] = [
+    Action::ShiftLeft,
+    Action::ShiftRight,
+    Action::RotateCw,
+    Action::RotateCcw,
+    Action::Rotate180,
+    Action::SoftDrop,
+];
+
+/// How many rows clear of the stack a piece has to be to be in the open air,
+/// enough for the biggest kicks and the biggest pieces.
+const AIR_GAP: i32 = 4;
+
+/// Where a piece is in the search, and the spin it got there with.
+type Node = (usize, usize, State, SpinType);
+
+/// How each node was best reached, as the node before it and the input made
+/// there, and how many presses that took in all.
+type Parents = HashMap<Node, (Option<(Node, Action)>, u32)>;
+
+fn node(piece: &ActivePiece, spin: SpinType) -> Node {
+    (piece.origin.0, piece.origin.1, piece.rotation, spin)
+}
+
+/// How much a spin is worth, to pick the best way into a spot.
+fn spin_rank(spin: SpinType) -> u8 {
+    match spin {
+        SpinType::Not => 0,
+        SpinType::Mini(_) => 1,
+        SpinType::Full(_) => 2,
+    }
+}
+
+impl Tetris {
+    /// Every place the active piece can lock, and every place the piece that
+    /// holding would bring out can, if holding is allowed.
+    ///
+    /// The search tries every shift, rotation (with its kicks) and soft drop
+    /// from where the piece is, so it finds tucks and spins as well as plain
+    /// drops. Placements are told apart by the cells they fill. Where a spot
+    /// can be reached more than one way, the placement uses the best spin it
+    /// can be reached with, and the fewest presses for that spin.
+    ///
+    /// The inputs are meant to be made all at once, as a bot would. Making
+    /// them slowly under [`LockReset::Move`] can run the piece out of lock
+    /// resets on the way.
+    ///
+    /// [`LockReset::Move`]: super::LockReset::Move
+    pub fn placements(&self) -> Vec<Placement> {
+        if self.is_game_over {
+            return Vec::new();
+        }
+        let (board, config) = (&self.board, &self.config);
+        let mut placements = search(board, config, self.active, self.last_was_spin, false);
+        let can_hold = self.held.0.is_none() || self.held.1;
+        let from_hold = self.held.0.or(self.queue.front().copied());
+        if let Some(from_hold) =
+            from_hold.filter(|&piece| can_hold && piece != self.active.tetromino)
+        {
+            let piece = ActivePiece::new(from_hold, &self.board, self.config.rotation);
+            if !self.blocked(&piece) {
+                placements.extend(search(board, config, piece, SpinType::Not, true));
+            }
+        }
+        placements
+    }
+}
+
+/// Searches out every placement of `start` on `board`, trying the positions
+/// that take the fewest presses to reach first. Every position the piece can
+/// reach gives the placement it would hard drop into, with spins judged by
+/// `config`'s rules.
+pub(super) fn search(
+    board: &Board,
+    config: &Config,
+    start: ActivePiece,
+    spin: SpinType,
+    hold: bool,
+) -> Vec<Placement> {
+    // Kept to trace the inputs back.
+    let mut parents: Parents = HashMap::new();
+    let mut done = HashSet::new();
+    // The nodes to search, as the presses taken to reach them and where
+    // they are in `found`, cheapest (then oldest) first.
+    let mut queue = BinaryHeap::new();
+    let mut found = vec![(start, spin)];
+    parents.insert(node(&start, spin), (None, 0));
+    queue.push(Reverse((0, 0)));
+    let mut placements: Vec<Placement> = Vec::new();
+    let mut by_cells: HashMap<[(usize, usize); 4], usize> = HashMap::new();
+    let surface = (0..board.height())
+        .find(|&row| board[row].iter().any(|&cell| cell != 0))
+        .unwrap_or(board.height());
+    while let Some(Reverse((cost, index))) = queue.pop() {
+        let (piece, spin) = found[index];
+        let here = node(&piece, spin);
+        if !done.insert(here) {
+            continue;
+        }
+        let mut landed = piece;
+        // Falling any distance loses the spin.
+        let spin_kept = !landed.soft_drop(board);
+        while landed.soft_drop(board) {}
+        let mut cells = landed
+            .get_squares()
+            .map(|(row, col)| (row as usize, col as usize));
+        cells.sort();
+        let landed_spin = if spin_kept { spin } else { SpinType::Not };
+        let better = match by_cells.get(&cells) {
+            Some(&i) => spin_rank(landed_spin) > spin_rank(placements[i].spin),
+            None => true,
+        };
+        if better {
+            let placement = Placement {
+                tetromino: piece.tetromino,
+                rotation: piece.rotation,
+                cells,
+                hold,
+                spin: landed_spin,
+                inputs: trace(&parents, here, hold),
+            };
+            match by_cells.get(&cells) {
+                Some(&i) => placements[i] = placement,
+                None => {
+                    by_cells.insert(cells, placements.len());
+                    placements.push(placement);
+                }
+            }
+        }
+        for action in MOVES {
+            let mut next = piece;
+            let next_spin = match action {
+                Action::ShiftLeft | Action::ShiftRight => next
+                    .shift(action == Action::ShiftLeft, board)
+                    .then_some(SpinType::Not),
+                Action::SoftDrop => {
+                    let dropped = next.soft_drop(board);
+                    // Shifts and turns work the same at any height in the
+                    // open air above the stack, so the piece falls
+                    // straight through it rather than being searched at
+                    // every row.
+                    while in_air(&next, surface) && next.soft_drop(board) {}
+                    dropped.then_some(SpinType::Not)
+                }
+                _ => {
+                    let kick = match action {
+                        Action::Rotate180 => next.rotate_180(board),
+                        _ => next.rotate(action == Action::RotateCw, board),
+                    };
+                    kick.map(|kick| {
+                        let final_kick = action != Action::Rotate180
+                            && next.system.system().final_kick() == Some(kick);
+                        next.detect_spin(board, config.spins, final_kick)
+                    })
+                }
+            };
+            let Some(next_spin) = next_spin else {
+                continue;
+            };
+            let there = node(&next, next_spin);
+            // Each soft drop is a press for every row fallen.
+            let presses = match action {
+                Action::SoftDrop => (there.0 - here.0) as u32,
+                _ => 1,
+            };
+            let better = parents
+                .get(&there)
+                .is_none_or(|&(_, best)| cost + presses < best);
+            if better {
+                parents.insert(there, (Some((here, action)), cost + presses));
+                queue.push(Reverse((cost + presses, found.len())));
+                found.push((next, next_spin));
+            }
+        }
+    }
+    placements
+}
+
+/// If `piece` is far enough above the highest block, `surface`, that no kick
+/// could bring it into contact with the stack.
+fn in_air(piece: &ActivePiece, surface: usize) -> bool {
+    piece
+        .get_squares()
+        .iter()
+        .all(|&(row, _)| row + AIR_GAP < surface as i32)
+}
+
+/// The inputs that reach `end`, finishing with a hard drop.
+fn trace(parents: &Parents, end: Node, hold: bool) -> Vec<Action> {
+    let mut inputs = Vec::new();
+    let mut at = end;
+    while let Some(&(Some((parent, action)), _)) = parents.get(&at) {
+        // A soft drop can fall more than one row in the search.
+        let presses = match action {
+            Action::SoftDrop => at.0 - parent.0,
+            _ => 1,
+        };
+        inputs.extend(std::iter::repeat_n(action, presses));
+        at = parent;
+    }
+    if hold {
+        inputs.push(Action::Hold);
+    }
+    inputs.reverse();
+    // Soft drops straight down onto the stack are the same as dropping from
+    // where they started.
+    while inputs.last() == Some(&Action::SoftDrop) {
+        inputs.pop();
+    }
+    inputs.push(Action::HardDrop);
+    inputs
+}
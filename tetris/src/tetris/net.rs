@@ -0,0 +1,392 @@
+use super::{Board, Config, GameEvent, Input, Tetris};
+use rand::{thread_rng, Rng};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::thread;
+use std::time::Duration;
+
+/// The seconds counted down before a match starts, by default.
+pub const DEFAULT_COUNTDOWN: u32 = 3;
+
+/// What a client tells the server during a match. Messages go over the
+/// connection as JSON, one per line.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ClientMessage {
+    /// Every input made on a frame, sent once the frame has been played.
+    Frame(Vec<Input>),
+    /// Lines of garbage sent by a clear, to go to the opponent.
+    Attack(u32),
+    /// The client's game is over.
+    GameOver,
+}
+
+/// What the server tells a client about its match.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ServerMessage {
+    /// An opponent was found. Both players' games are started from the same
+    /// seed and config, and `player` is which of the two this client is.
+    Match {
+        seed: u64,
+        config: Config,
+        player: usize,
+    },
+    /// The seconds left before the match starts.
+    Countdown(u32),
+    /// The match has started.
+    Start,
+    /// A frame the opponent played, to be played on the client's copy of the
+    /// opponent's game.
+    OpponentFrame(Vec<Input>),
+    /// Lines of garbage the opponent sent.
+    Garbage(u32),
+    /// The match is over, and the player with this number won it.
+    Winner(usize),
+}
+
+/// Writes a message to a connection as a line of JSON.
+fn send<T: Serialize>(mut stream: &TcpStream, message: &T) -> io::Result<()> {
+    let mut line = serde_json::to_vec(message)?;
+    line.push(b'\n');
+    stream.write_all(&line)
+}
+
+/// Reads messages from a connection on a new thread, and sends each of them
+/// down `sender` along with `tag`. `None` is sent once the connection closes,
+/// or sends something that isn't a message.
+fn spawn_reader<T, K>(stream: &TcpStream, tag: K, sender: Sender<(K, Option<T>)>) -> io::Result<()>
+where
+    T: DeserializeOwned + Send + 'static,
+    K: Copy + Send + 'static,
+{
+    let reader = BufReader::new(stream.try_clone()?);
+    thread::spawn(move || {
+        for line in reader.lines() {
+            let Some(message) = line.ok().and_then(|line| serde_json::from_str(&line).ok()) else {
+                break;
+            };
+            if sender.send((tag, Some(message))).is_err() {
+                return;
+            }
+        }
+        let _ = sender.send((tag, None));
+    });
+    Ok(())
+}
+
+/// A headless server that pairs up players as they connect, and referees
+/// their matches. The server never plays the games itself: it counts the
+/// match in, passes each player's frames and garbage to the other, and
+/// declares a winner as soon as either player's game is over or they leave.
+pub struct Server {
+    listener: TcpListener,
+    config: Config,
+    countdown: u32,
+}
+impl Server {
+    /// Starts listening on `addr`. Every match is played with `config`.
+    pub fn bind<A: ToSocketAddrs>(addr: A, config: Config) -> io::Result<Self> {
+        Ok(Server {
+            listener: TcpListener::bind(addr)?,
+            config,
+            countdown: DEFAULT_COUNTDOWN,
+        })
+    }
+
+    /// The address the server is listening on.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Sets how many seconds are counted down before each match starts.
+    pub fn set_countdown(&mut self, seconds: u32) {
+        self.countdown = seconds;
+    }
+
+    /// Waits for two players to connect.
+    fn accept_pair(&self) -> io::Result<[TcpStream; 2]> {
+        let (first, _) = self.listener.accept()?;
+        let (second, _) = self.listener.accept()?;
+        for stream in [&first, &second] {
+            stream.set_nodelay(true)?;
+        }
+        Ok([first, second])
+    }
+
+    /// Waits for two players, and plays a match between them. Returns the
+    /// number of the player who won.
+    pub fn run_match(&self) -> io::Result<usize> {
+        let streams = self.accept_pair()?;
+        referee(streams, self.config, self.countdown)
+    }
+
+    /// Pairs up players forever, playing each match on its own thread.
+    pub fn serve(&self) -> io::Result<()> {
+        loop {
+            let streams = self.accept_pair()?;
+            let (config, countdown) = (self.config, self.countdown);
+            thread::spawn(move || referee(streams, config, countdown));
+        }
+    }
+}
+
+/// Plays a match between two connected players, and returns the number of
+/// the player who won.
+fn referee(streams: [TcpStream; 2], config: Config, countdown: u32) -> io::Result<usize> {
+    let (sender, messages) = mpsc::channel();
+    for (player, stream) in streams.iter().enumerate() {
+        spawn_reader::<ClientMessage, _>(stream, player, sender.clone())?;
+    }
+    drop(sender);
+    // A player that can't be written to has left, which their reader picks
+    // up on, so failed writes are left for it to deal with.
+    let broadcast = |message: &ServerMessage| {
+        for stream in &streams {
+            let _ = send(stream, message);
+        }
+    };
+    let seed = thread_rng().gen();
+    for (player, stream) in streams.iter().enumerate() {
+        let _ = send(
+            stream,
+            &ServerMessage::Match {
+                seed,
+                config,
+                player,
+            },
+        );
+    }
+    for seconds in (1..=countdown).rev() {
+        broadcast(&ServerMessage::Countdown(seconds));
+        thread::sleep(Duration::from_secs(1));
+    }
+    broadcast(&ServerMessage::Start);
+    // Garbage is passed on no bigger than the board, however much a player
+    // says they sent.
+    let most_garbage = (config.height + config.buffer) as u32;
+    let mut winner = None;
+    for (player, message) in &messages {
+        let opponent = 1 - player;
+        let relay = match message {
+            Some(ClientMessage::Frame(inputs)) => ServerMessage::OpponentFrame(inputs),
+            Some(ClientMessage::Attack(lines)) => ServerMessage::Garbage(lines.min(most_garbage)),
+            // Leaving a match forfeits it.
+            Some(ClientMessage::GameOver) | None => {
+                winner = Some(opponent);
+                break;
+            }
+        };
+        let _ = send(&streams[opponent], &relay);
+    }
+    let winner = winner.ok_or_else(|| io::Error::from(io::ErrorKind::ConnectionAborted))?;
+    broadcast(&ServerMessage::Winner(winner));
+    // Hanging up lets the players know the match is over for good.
+    for stream in &streams {
+        let _ = stream.shutdown(Shutdown::Write);
+    }
+    Ok(winner)
+}
+
+/// A connection to a [`Server`].
+pub struct Client {
+    stream: TcpStream,
+    messages: Receiver<((), Option<ServerMessage>)>,
+}
+impl Client {
+    pub fn connect<A: ToSocketAddrs>(addr: A) -> io::Result<Self> {
+        let stream = TcpStream::connect(addr)?;
+        stream.set_nodelay(true)?;
+        let (sender, messages) = mpsc::channel();
+        spawn_reader(&stream, (), sender)?;
+        Ok(Client { stream, messages })
+    }
+
+    pub fn send(&self, message: &ClientMessage) -> io::Result<()> {
+        send(&self.stream, message)
+    }
+
+    /// Waits for the next message from the server. Returns `None` once the
+    /// server has gone.
+    pub fn recv(&self) -> Option<ServerMessage> {
+        self.messages.recv().ok().and_then(|(_, message)| message)
+    }
+
+    /// The next message from the server, if one has come in. Errors once the
+    /// server has gone.
+    pub fn try_recv(&self) -> io::Result<Option<ServerMessage>> {
+        match self.messages.try_recv() {
+            Ok(((), Some(message))) => Ok(Some(message)),
+            Err(TryRecvError::Empty) => Ok(None),
+            Ok(((), None)) | Err(TryRecvError::Disconnected) => {
+                Err(io::Error::from(io::ErrorKind::ConnectionAborted))
+            }
+        }
+    }
+}
+
+impl Drop for Client {
+    fn drop(&mut self) {
+        // The reader thread has its own handle on the connection, so it has to
+        // be closed by hand for the server to see the client go.
+        let _ = self.stream.shutdown(Shutdown::Both);
+    }
+}
+
+/// Where an online match is at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchState {
+    /// Waiting for the server to count the match in.
+    Waiting,
+    /// The seconds left before the match starts.
+    Countdown(u32),
+    Playing,
+    Won,
+    Lost,
+    /// The server went away before the match was decided.
+    Disconnected,
+}
+
+/// A match against another player through a [`Server`], from one player's
+/// side. Alongside the player's own game, it keeps a copy of the opponent's
+/// game, played from the frames the server passes on. Both games start from
+/// the same seed, so the copy plays out exactly as the opponent's does.
+pub struct OnlineGame {
+    client: Client,
+    local: Tetris,
+    opponent: Tetris,
+    player: usize,
+    state: MatchState,
+    /// The number of the local game's recorded inputs sent to the server.
+    sent: usize,
+    /// If the server's been told the local game is over.
+    finished: bool,
+}
+impl OnlineGame {
+    /// Connects to the server at `addr`, and waits for it to find an
+    /// opponent.
+    pub fn connect<A: ToSocketAddrs>(addr: A) -> io::Result<Self> {
+        OnlineGame::join(Client::connect(addr)?)
+    }
+
+    /// Waits for the server `client` is connected to to find an opponent.
+    pub fn join(client: Client) -> io::Result<Self> {
+        let Some(ServerMessage::Match {
+            seed,
+            config,
+            player,
+        }) = client.recv()
+        else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "the server didn't start a match",
+            ));
+        };
+        if let Err(e) = Board::check_size(config.width, config.height, config.buffer) {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, e));
+        }
+        let mut local = Tetris::with_config(config, Some(seed));
+        // Recording is how the frames played get back out of the game.
+        local.record();
+        Ok(OnlineGame {
+            client,
+            local,
+            opponent: Tetris::with_config(config, Some(seed)),
+            player,
+            state: MatchState::Waiting,
+            sent: 0,
+            finished: false,
+        })
+    }
+
+    /// This player's own game.
+    pub fn local(&self) -> &Tetris {
+        &self.local
+    }
+
+    /// This player's own game, for making inputs on. Inputs only count while
+    /// the match is [`MatchState::Playing`].
+    pub fn local_mut(&mut self) -> &mut Tetris {
+        &mut self.local
+    }
+
+    /// The copy of the opponent's game.
+    pub fn opponent(&self) -> &Tetris {
+        &self.opponent
+    }
+
+    /// Which of the match's two players this is.
+    pub fn player(&self) -> usize {
+        self.player
+    }
+
+    pub fn state(&self) -> MatchState {
+        self.state
+    }
+
+    /// Deals with everything the server has sent, then plays a frame of the
+    /// local game if the match is on, and sends it to the server. Returns the
+    /// local game's events from the frame.
+    pub fn step(&mut self) -> io::Result<Vec<GameEvent>> {
+        loop {
+            let message = match self.client.try_recv() {
+                Ok(Some(message)) => message,
+                Ok(None) => break,
+                // The server hangs up once it's declared a winner.
+                Err(_) if matches!(self.state, MatchState::Won | MatchState::Lost) => {
+                    return Ok(Vec::new());
+                }
+                Err(e) => {
+                    self.state = MatchState::Disconnected;
+                    return Err(e);
+                }
+            };
+            match message {
+                ServerMessage::Countdown(seconds) => self.state = MatchState::Countdown(seconds),
+                ServerMessage::Start => self.state = MatchState::Playing,
+                ServerMessage::OpponentFrame(inputs) => {
+                    for input in inputs {
+                        input.apply_to(&mut self.opponent);
+                    }
+                    // As with the local game, the last frame ends early if
+                    // an input ends the game.
+                    if !self.opponent.is_game_over {
+                        self.opponent.frame_advance();
+                    }
+                    self.opponent.take_events();
+                }
+                ServerMessage::Garbage(lines) => self.local.queue_garbage(lines),
+                ServerMessage::Winner(winner) if winner == self.player => {
+                    self.state = MatchState::Won;
+                }
+                ServerMessage::Winner(_) => self.state = MatchState::Lost,
+                ServerMessage::Match { .. } => {}
+            }
+        }
+        if self.state != MatchState::Playing || self.finished {
+            return Ok(Vec::new());
+        }
+        // The game can end between frames, from an input that locks a piece.
+        if !self.local.is_game_over {
+            self.local.frame_advance();
+        }
+        let inputs = self.local.get_replay().map_or(&[][..], |r| r.get_inputs());
+        let frame = inputs[self.sent..]
+            .iter()
+            .map(|&(_, input)| input)
+            .collect();
+        self.sent = inputs.len();
+        self.client.send(&ClientMessage::Frame(frame))?;
+        let events = self.local.take_events();
+        for event in &events {
+            if let GameEvent::Attack(lines) = event {
+                self.client.send(&ClientMessage::Attack(*lines))?;
+            }
+        }
+        if self.local.is_game_over {
+            self.client.send(&ClientMessage::GameOver)?;
+            self.finished = true;
+        }
+        Ok(events)
+    }
+}
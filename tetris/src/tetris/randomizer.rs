@@ -0,0 +1,252 @@
+use super::Tetromino;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use strum::IntoEnumIterator;
+use strum_macros::{Display, EnumIter, EnumString};
+
+/// Every piece, in the order that the engine numbers them.
+const PIECES: [Tetromino; 7] = [
+    Tetromino::I,
+    Tetromino::O,
+    Tetromino::T,
+    Tetromino::J,
+    Tetromino::L,
+    Tetromino::S,
+    Tetromino::Z,
+];
+
+/// A randomizer decides the order in which pieces are dealt to the player.
+/// `Tetris` pulls a new piece from its randomizer every time a piece is added
+/// to the back of the queue.
+pub trait Randomizer {
+    /// Produces the next piece in the sequence.
+    fn next_piece(&mut self) -> Tetromino;
+
+    /// Captures the complete state of the randomizer, RNG included, so that a
+    /// saved game keeps dealing the same pieces once it's resumed.
+    fn save(&self) -> SavedRandomizer;
+}
+
+/// The state of any of the engine's randomizers, in a form that can be
+/// written out along with a saved game.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum SavedRandomizer {
+    Bag(Bag),
+    Memoryless(Memoryless),
+    History(History),
+    Nes(Nes),
+}
+impl SavedRandomizer {
+    /// Turns the saved state back into a working randomizer.
+    pub fn restore(self) -> Box<dyn Randomizer> {
+        match self {
+            SavedRandomizer::Bag(r) => Box::new(r),
+            SavedRandomizer::Memoryless(r) => Box::new(r),
+            SavedRandomizer::History(r) => Box::new(r),
+            SavedRandomizer::Nes(r) => Box::new(r),
+        }
+    }
+}
+
+/// The randomizers that ship with the engine. Each can be built from a seed,
+/// which is how the rest of the engine (and `tetroxide`) chooses one.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Default,
+    EnumIter,
+    EnumString,
+    Display,
+    Serialize,
+    Deserialize,
+)]
+#[strum(serialize_all = "lowercase")]
+pub enum RandomizerKind {
+    /// The Guideline "7-bag"; every run of 7 pieces has one of each.
+    #[default]
+    #[strum(serialize = "7bag")]
+    SevenBag,
+    /// Two copies of every piece shuffled together.
+    #[strum(serialize = "14bag")]
+    FourteenBag,
+    /// Every piece is picked independently of the ones before it.
+    Random,
+    /// The Grand Master style 4-piece history with rerolls.
+    Tgm,
+    /// The original NES algorithm, which rerolls a repeat once.
+    Nes,
+}
+impl RandomizerKind {
+    /// Creates the randomizer this kind describes, seeded with `seed`.
+    pub fn build(self, seed: u64) -> Box<dyn Randomizer> {
+        match self {
+            RandomizerKind::SevenBag => Box::new(Bag::new(seed, 1)),
+            RandomizerKind::FourteenBag => Box::new(Bag::new(seed, 2)),
+            RandomizerKind::Random => Box::new(Memoryless::new(seed)),
+            RandomizerKind::Tgm => Box::new(History::new(seed)),
+            RandomizerKind::Nes => Box::new(Nes::new(seed)),
+        }
+    }
+}
+
+/// A bag is a data structure used by Tetris to represent the queue of incoming
+/// pieces. Every bag owns its own seeded RNG, so that two bags made from the
+/// same seed will always hand out the exact same sequence of pieces.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Bag {
+    pieces: Vec<Tetromino>,
+    copies: usize,
+    rng: ChaCha8Rng,
+}
+impl Bag {
+    /// Creates a new bag with randomly shuffled Tetromino's. A bag always has
+    /// at most `7 * copies` tetromino's inside of it, `copies` of each of the
+    /// main pieces, as to ensure that a player isn't constantly getting the
+    /// same tetromino over and over, but there is still an element of
+    /// randomness.
+    ///
+    /// The shuffling is driven by a ChaCha8 RNG seeded with `seed`, which is
+    /// guaranteed to give the same stream on every platform.
+    pub fn new(seed: u64, copies: usize) -> Self {
+        let mut bag = Bag {
+            pieces: Vec::with_capacity(7 * copies),
+            copies,
+            rng: ChaCha8Rng::seed_from_u64(seed),
+        };
+        bag.fill();
+        bag
+    }
+    /// Fills an empty bag with randomly shuffled Tetromino's. If the bag isn't
+    /// empty, it does nothing, as it is unable to fill a bag already full.
+    /// Otherwise, the bag is filled up with shuffled elements.
+    fn fill(&mut self) {
+        if !self.pieces.is_empty() {
+            return;
+        }
+        for _ in 0..self.copies {
+            self.pieces.extend(Tetromino::iter());
+        }
+        self.pieces.shuffle(&mut self.rng);
+    }
+}
+impl Randomizer for Bag {
+    fn next_piece(&mut self) -> Tetromino {
+        // We try to fill it regardless of anything...
+        self.fill();
+        // Then we simply pop from the top of the bag!
+        self.pieces.pop().unwrap()
+    }
+
+    fn save(&self) -> SavedRandomizer {
+        SavedRandomizer::Bag(self.clone())
+    }
+}
+
+/// Picks every piece uniformly at random, with no memory of what came before.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Memoryless {
+    rng: ChaCha8Rng,
+}
+impl Memoryless {
+    pub fn new(seed: u64) -> Self {
+        Memoryless {
+            rng: ChaCha8Rng::seed_from_u64(seed),
+        }
+    }
+}
+impl Randomizer for Memoryless {
+    fn next_piece(&mut self) -> Tetromino {
+        PIECES[self.rng.gen_range(0..7)]
+    }
+
+    fn save(&self) -> SavedRandomizer {
+        SavedRandomizer::Memoryless(self.clone())
+    }
+}
+
+/// The number of times the TGM randomizer will reroll a piece that is still in
+/// its history.
+const TGM_ROLLS: usize = 4;
+
+/// The randomizer from [Tetris: The Grand Master](https://tetris.wiki/TGM_randomizer).
+/// It remembers the last 4 pieces dealt, and rerolls up to 4 times after the
+/// first roll to avoid handing out one of them again. The history starts full
+/// of `Z` pieces, and the first piece is never an `S`, `Z` or `O`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct History {
+    history: VecDeque<Tetromino>,
+    first: bool,
+    rng: ChaCha8Rng,
+}
+impl History {
+    pub fn new(seed: u64) -> Self {
+        History {
+            history: VecDeque::from([Tetromino::Z; 4]),
+            first: true,
+            rng: ChaCha8Rng::seed_from_u64(seed),
+        }
+    }
+}
+impl Randomizer for History {
+    fn next_piece(&mut self) -> Tetromino {
+        let piece = if self.first {
+            self.first = false;
+            [Tetromino::I, Tetromino::T, Tetromino::J, Tetromino::L][self.rng.gen_range(0..4)]
+        } else {
+            let mut piece = PIECES[self.rng.gen_range(0..7)];
+            for _ in 0..TGM_ROLLS {
+                if !self.history.contains(&piece) {
+                    break;
+                }
+                piece = PIECES[self.rng.gen_range(0..7)];
+            }
+            piece
+        };
+        self.history.pop_front();
+        self.history.push_back(piece);
+        piece
+    }
+
+    fn save(&self) -> SavedRandomizer {
+        SavedRandomizer::History(self.clone())
+    }
+}
+
+/// The randomizer from the [NES version of Tetris](https://tetris.wiki/Tetris_(NES,_Nintendo)).
+/// It rolls an 8-sided die where the 8th side (or a repeat of the previous
+/// piece) means "roll again", and it only ever rerolls once.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Nes {
+    last: Option<Tetromino>,
+    rng: ChaCha8Rng,
+}
+impl Nes {
+    pub fn new(seed: u64) -> Self {
+        Nes {
+            last: None,
+            rng: ChaCha8Rng::seed_from_u64(seed),
+        }
+    }
+}
+impl Randomizer for Nes {
+    fn next_piece(&mut self) -> Tetromino {
+        let roll = self.rng.gen_range(0..8);
+        let piece = if roll == 7 || self.last == Some(PIECES[roll]) {
+            PIECES[self.rng.gen_range(0..7)]
+        } else {
+            PIECES[roll]
+        };
+        self.last = Some(piece);
+        piece
+    }
+
+    fn save(&self) -> SavedRandomizer {
+        SavedRandomizer::Nes(self.clone())
+    }
+}
@@ -0,0 +1,174 @@
+use super::{Action, Config, Tetris};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+/// The version of the replay format written by this build. Replays with any
+/// other version are refused when loading, rather than played back wrong.
+pub const REPLAY_VERSION: u32 = 6;
+
+/// Anything the player did that changes how the game plays out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Input {
+    Action(Action),
+    /// An action being pressed (`true`) or released, through
+    /// [`Tetris::set_held`].
+    Held(Action, bool),
+    SetLevel(u32),
+    /// Lines of garbage sent to the game, through [`Tetris::queue_garbage`].
+    Garbage(u32),
+}
+impl Input {
+    /// Makes the input on `tetris`, the same way it was made when recorded.
+    pub fn apply_to(self, tetris: &mut Tetris) {
+        match self {
+            Input::Action(action) => {
+                tetris.apply(action);
+            }
+            Input::Held(action, held) => tetris.set_held(action, held),
+            Input::SetLevel(level) => tetris.set_level(level),
+            Input::Garbage(lines) => tetris.queue_garbage(lines),
+        }
+    }
+}
+
+/// A recording of a single game. It stores everything needed to rebuild the
+/// game from scratch (the seed and config), and every input along with the
+/// frame it happened on, so that playing it back through the engine will
+/// reproduce the game exactly.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Replay {
+    version: u32,
+    seed: u64,
+    config: Config,
+    inputs: Vec<(u64, Input)>,
+    frames: u64,
+}
+impl Replay {
+    pub(super) fn new(seed: u64, config: Config) -> Self {
+        Replay {
+            version: REPLAY_VERSION,
+            seed,
+            config,
+            inputs: Vec::new(),
+            frames: 0,
+        }
+    }
+
+    pub(super) fn push(&mut self, frame: u64, input: Input) {
+        self.inputs.push((frame, input));
+    }
+
+    pub(super) fn finish(&mut self, frames: u64) {
+        self.frames = frames;
+    }
+
+    /// The seed that the recorded game was started with.
+    pub fn get_seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// The total number of frames in the recording.
+    pub fn get_frames(&self) -> u64 {
+        self.frames
+    }
+
+    /// Every recorded input, alongside the frame it happened on.
+    pub fn get_inputs(&self) -> &[(u64, Input)] {
+        &self.inputs
+    }
+
+    /// Writes the replay out in its file format.
+    pub fn write<W: Write>(&self, writer: W) -> io::Result<()> {
+        serde_json::to_writer(writer, self)?;
+        Ok(())
+    }
+
+    /// Reads a replay back in, refusing any replay from a different version
+    /// of the format.
+    pub fn read<R: Read>(reader: R) -> io::Result<Self> {
+        let replay: Replay = serde_json::from_reader(reader)?;
+        if replay.version != REPLAY_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "replay is version {}, but only version {} is supported",
+                    replay.version, REPLAY_VERSION
+                ),
+            ));
+        }
+        Ok(replay)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write(&mut writer)?;
+        writer.flush()
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Replay::read(BufReader::new(File::open(path)?))
+    }
+
+    /// Creates a fresh copy of the game that the replay was recorded from.
+    pub fn new_game(&self) -> Tetris {
+        Tetris::with_config(self.config, Some(self.seed))
+    }
+
+    /// Starts playing back the replay. The playback should be stepped along
+    /// with a game made from `new_game`.
+    pub fn playback(&self) -> Playback<'_> {
+        Playback {
+            replay: self,
+            next: 0,
+        }
+    }
+
+    /// Plays the whole replay back, and gives back the final game.
+    pub fn play_to_end(&self) -> Tetris {
+        let (mut tetris, mut playback) = (self.new_game(), self.playback());
+        while !playback.is_finished(&tetris) {
+            playback.step(&mut tetris);
+        }
+        tetris
+    }
+}
+
+/// Plays a replay back through the engine, one frame at a time.
+pub struct Playback<'a> {
+    replay: &'a Replay,
+    next: usize,
+}
+impl Playback<'_> {
+    /// If every frame of the recording has been played on `tetris`, or the
+    /// game is over.
+    pub fn is_finished(&self, tetris: &Tetris) -> bool {
+        let frame = tetris.get_frame();
+        // The recording can stop partway through its last frame, like when an
+        // input ends the game, leaving inputs to play on that frame.
+        let inputs_left =
+            matches!(self.replay.inputs.get(self.next), Some(&(at, _)) if at == frame);
+        tetris.is_game_over || (frame >= self.replay.frames && !inputs_left)
+    }
+
+    /// Applies every input recorded on the current frame, then advances the
+    /// game by a frame, unless that's the last one. Does nothing once the
+    /// replay is finished.
+    pub fn step(&mut self, tetris: &mut Tetris) {
+        if self.is_finished(tetris) {
+            return;
+        }
+        let frame = tetris.get_frame();
+        while let Some(&(at, input)) = self.replay.inputs.get(self.next) {
+            if at != frame {
+                break;
+            }
+            input.apply_to(tetris);
+            self.next += 1;
+        }
+        if frame < self.replay.frames {
+            tetris.frame_advance();
+        }
+    }
+}
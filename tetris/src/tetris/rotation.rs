@@ -0,0 +1,428 @@
+use super::{Board, Pos, State, Tetromino};
+use serde::{Deserialize, Serialize};
+use strum_macros::{Display, EnumIter, EnumString};
+
+/// A rotation system decides what each piece looks like in each of its
+/// orientations, and where a piece is allowed to "kick" to when a plain
+/// rotation is blocked.
+///
+/// Shapes are given as `(x, y)` offsets from the piece's origin, where `x`
+/// grows to the right and `y` grows downwards. Kicks are given in the same
+/// coordinates.
+pub trait RotationSystem {
+    /// The cells a piece covers in a given orientation.
+    fn shape(&self, tetromino: Tetromino, rotation: State) -> [(i32, i32); 4];
+
+    /// The orientation that pieces spawn in.
+    fn spawn_rotation(&self, _tetromino: Tetromino) -> State {
+        State::Up
+    }
+
+    /// The offsets to try, in order, when rotating a piece at `origin` from
+    /// `from` to `to`. The first offset that fits is used, and if none of them
+    /// fit the rotation fails. A plain rotation is the offset `(0, 0)`.
+    fn kicks(
+        &self,
+        tetromino: Tetromino,
+        from: State,
+        to: State,
+        origin: Pos,
+        board: &Board,
+    ) -> Vec<(i32, i32)>;
+
+    /// The offsets to try, in order, when turning a piece at `origin` a half
+    /// turn from `from` to `to`. Systems without 180 kicks only try the plain
+    /// rotation.
+    fn kicks_180(
+        &self,
+        _tetromino: Tetromino,
+        _from: State,
+        _to: State,
+        _origin: Pos,
+        _board: &Board,
+    ) -> Vec<(i32, i32)> {
+        vec![(0, 0)]
+    }
+
+    /// The index of the quarter turn kick that makes a T-spin a full one
+    /// however many corners are filled, as with the Guideline's T-spin
+    /// triples and fins. Systems without one go by the corners alone.
+    fn final_kick(&self) -> Option<usize> {
+        None
+    }
+}
+
+/// The rotation systems that ship with the engine.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Default,
+    EnumIter,
+    EnumString,
+    Display,
+    Serialize,
+    Deserialize,
+)]
+#[strum(serialize_all = "lowercase")]
+pub enum RotationSystemKind {
+    /// The Guideline's Super Rotation System.
+    #[default]
+    Srs,
+    /// SRS with symmetric kicks for the `I` piece, as in Tetr.io.
+    #[strum(serialize = "srs+")]
+    SrsPlus,
+    /// The Arika Rotation System from the Grand Master games.
+    Ars,
+    /// The Nintendo Rotation System from the NES and Game Boy games.
+    Nrs,
+    /// The rotation from Sega's 1988 arcade Tetris.
+    Sega,
+}
+impl RotationSystemKind {
+    /// The rotation system this kind describes.
+    pub fn system(self) -> &'static dyn RotationSystem {
+        match self {
+            RotationSystemKind::Srs => &Srs,
+            RotationSystemKind::SrsPlus => &SrsPlus,
+            RotationSystemKind::Ars => &Ars,
+            RotationSystemKind::Nrs => &Nrs,
+            RotationSystemKind::Sega => &Sega,
+        }
+    }
+}
+
+/// The [Super Rotation System](https://tetris.wiki/Super_Rotation_System).
+/// In short summary, each piece goes through 5 different tests when it
+/// attempts to rotate - the 1st being the basic rotational state, and the
+/// following 4 being various "wall kick" states. This is what enables complex
+/// moves like [t-spins](https://tetris.com/article/70/how-to-perform-a-t-spin-in-tetris)
+/// to properly work.
+pub struct Srs;
+impl RotationSystem for Srs {
+    fn shape(&self, tetromino: Tetromino, rotation: State) -> [(i32, i32); 4] {
+        match tetromino {
+            // The I piece rotates about the centre of its 4x4 box, rather than
+            // about a cell, so its origin is the cell just up and left of it.
+            Tetromino::I => match rotation {
+                State::Up => [(-1, 0), (0, 0), (1, 0), (2, 0)],
+                State::Right => [(1, -1), (1, 0), (1, 1), (1, 2)],
+                State::Down => [(-1, 1), (0, 1), (1, 1), (2, 1)],
+                State::Left => [(0, -1), (0, 0), (0, 1), (0, 2)],
+            },
+            Tetromino::O => [(0, -1), (1, -1), (0, 0), (1, 0)],
+            Tetromino::T => match rotation {
+                State::Up => [(0, -1), (-1, 0), (0, 0), (1, 0)],
+                State::Right => [(0, -1), (0, 0), (1, 0), (0, 1)],
+                State::Down => [(-1, 0), (0, 0), (1, 0), (0, 1)],
+                State::Left => [(0, -1), (-1, 0), (0, 0), (0, 1)],
+            },
+            Tetromino::J => match rotation {
+                State::Up => [(-1, -1), (-1, 0), (0, 0), (1, 0)],
+                State::Right => [(0, -1), (1, -1), (0, 0), (0, 1)],
+                State::Down => [(-1, 0), (0, 0), (1, 0), (1, 1)],
+                State::Left => [(0, 1), (0, 0), (-1, 1), (0, -1)],
+            },
+            Tetromino::L => match rotation {
+                State::Up => [(1, -1), (-1, 0), (0, 0), (1, 0)],
+                State::Right => [(0, -1), (0, 0), (0, 1), (1, 1)],
+                State::Down => [(-1, 0), (0, 0), (1, 0), (-1, 1)],
+                State::Left => [(-1, -1), (0, -1), (0, 0), (0, 1)],
+            },
+            Tetromino::S => match rotation {
+                State::Up => [(0, -1), (1, -1), (-1, 0), (0, 0)],
+                State::Right => [(0, -1), (0, 0), (1, 0), (1, 1)],
+                State::Down => [(0, 0), (1, 0), (-1, 1), (0, 1)],
+                State::Left => [(-1, -1), (-1, 0), (0, 0), (0, 1)],
+            },
+            Tetromino::Z => match rotation {
+                State::Up => [(-1, -1), (0, -1), (0, 0), (1, 0)],
+                State::Right => [(1, -1), (1, 0), (0, 0), (0, 1)],
+                State::Down => [(-1, 0), (0, 0), (0, 1), (1, 1)],
+                State::Left => [(0, -1), (0, 0), (-1, 0), (-1, 1)],
+            },
+        }
+    }
+
+    fn kicks(
+        &self,
+        tetromino: Tetromino,
+        from: State,
+        to: State,
+        _origin: Pos,
+        _board: &Board,
+    ) -> Vec<(i32, i32)> {
+        let mut kicks = vec![(0, 0)];
+        kicks.extend(match tetromino {
+            Tetromino::I => srs_i_kicks(from, to),
+            _ => srs_kicks(tetromino, from, to),
+        });
+        kicks
+    }
+
+    fn kicks_180(
+        &self,
+        _tetromino: Tetromino,
+        from: State,
+        _to: State,
+        _origin: Pos,
+        _board: &Board,
+    ) -> Vec<(i32, i32)> {
+        srs_180_kicks(from)
+    }
+
+    /// The last of the four kicks tried after the plain rotation.
+    fn final_kick(&self) -> Option<usize> {
+        Some(4)
+    }
+}
+
+/// SRS itself has no half turns, so both SRS and SRS+ use the 180 kicks from
+/// [Tetr.io](https://tetris.wiki/TETR.IO#Rotation_system), which are the same
+/// for every piece.
+fn srs_180_kicks(from: State) -> Vec<(i32, i32)> {
+    match from {
+        State::Up => vec![(0, 0), (0, -1), (1, -1), (-1, -1), (1, 0), (-1, 0)],
+        State::Down => vec![(0, 0), (0, 1), (-1, 1), (1, 1), (-1, 0), (1, 0)],
+        State::Right => vec![(0, 0), (1, 0), (1, -2), (1, -1), (0, -2), (0, -1)],
+        State::Left => vec![(0, 0), (-1, 0), (-1, -2), (-1, -1), (0, -2), (0, -1)],
+    }
+}
+
+/// The 4 wall kicks that SRS tries for every piece other than `I`, after the
+/// basic rotation.
+fn srs_kicks(tetromino: Tetromino, from: State, to: State) -> Vec<(i32, i32)> {
+    // "Kick data" refers to the possible offset values that can be used for the 4 kick states.
+    // There are 8 total different offset value sets; 4 of which are inverted from the other 4.
+    let kick_data = vec![(-1, 0), (-1, -1), (0, 2), (-1, 2)];
+    match tetromino {
+        Tetromino::O => vec![], /* O Tetromino's have no rotational logic. */
+        _ => match (from, to) {
+            // CW from Spawn State OR CCW to Inverted Spawn State
+            (State::Up, State::Right) | (State::Down, State::Right) => kick_data,
+            // CCW to Spawn State OR CW from Inverted Spawn State
+            (State::Right, State::Up) | (State::Right, State::Down) => {
+                kick_data.into_iter().map(|(x, y)| (-x, -y)).collect()
+            }
+            // CW from Inverted Spawn State OR CCW from Spawn State
+            (State::Down, State::Left) | (State::Up, State::Left) => {
+                kick_data.into_iter().map(|(x, y)| (-x, y)).collect()
+            }
+            // CCW to Inverted Spawn State OR CW to Spawn State
+            (State::Left, State::Down) | (State::Left, State::Up) => {
+                kick_data.into_iter().map(|(x, y)| (x, -y)).collect()
+            }
+            _ => vec![],
+        },
+    }
+}
+
+/// The 4 wall kicks that SRS tries for the `I` piece, after the basic rotation.
+fn srs_i_kicks(from: State, to: State) -> Vec<(i32, i32)> {
+    let kick_data_i1 = vec![(-2, 0), (1, 0), (-2, 1), (1, -2)];
+    let kick_data_i2 = vec![(-1, 0), (2, 0), (-1, -2), (2, 1)];
+    match (from, to) {
+        // CW from Spawn State OR CCW to Inverted Spawn State
+        (State::Up, State::Right) | (State::Left, State::Down) => kick_data_i1,
+        // CCW to Spawn State OR CW from Inverted Spawn State
+        (State::Right, State::Up) | (State::Down, State::Left) => {
+            kick_data_i1.into_iter().map(|(x, y)| (-x, -y)).collect()
+        }
+        // CW to Inverted Spawn State OR CCW from Spawn State
+        (State::Right, State::Down) | (State::Up, State::Left) => kick_data_i2,
+        // CCW to Inverted Spawn State OR CW to Spawn State
+        (State::Down, State::Right) | (State::Left, State::Up) => {
+            kick_data_i2.into_iter().map(|(x, y)| (-x, -y)).collect()
+        }
+        _ => vec![],
+    }
+}
+
+/// SRS, but with the `I` piece kicks from [Tetr.io](https://tetris.wiki/TETR.IO#Rotation_system),
+/// which mirror each other between left and right rotations.
+pub struct SrsPlus;
+impl RotationSystem for SrsPlus {
+    fn shape(&self, tetromino: Tetromino, rotation: State) -> [(i32, i32); 4] {
+        Srs.shape(tetromino, rotation)
+    }
+
+    fn kicks(
+        &self,
+        tetromino: Tetromino,
+        from: State,
+        to: State,
+        origin: Pos,
+        board: &Board,
+    ) -> Vec<(i32, i32)> {
+        if tetromino != Tetromino::I {
+            return Srs.kicks(tetromino, from, to, origin, board);
+        }
+        let kicks = match (from, to) {
+            (State::Up, State::Right) => [(1, 0), (-2, 0), (-2, 1), (1, -2)],
+            (State::Right, State::Up) => [(-1, 0), (2, 0), (-1, 2), (2, -1)],
+            (State::Right, State::Down) => [(-1, 0), (2, 0), (-1, -2), (2, 1)],
+            (State::Down, State::Right) => [(-2, 0), (1, 0), (-2, -1), (1, 2)],
+            (State::Down, State::Left) => [(2, 0), (-1, 0), (2, -1), (-1, 2)],
+            (State::Left, State::Down) => [(1, 0), (-2, 0), (1, -2), (-2, 1)],
+            (State::Left, State::Up) => [(1, 0), (-2, 0), (1, 2), (-2, -1)],
+            (State::Up, State::Left) => [(-1, 0), (2, 0), (-1, -2), (2, 1)],
+            _ => return vec![(0, 0)],
+        };
+        let mut all = vec![(0, 0)];
+        all.extend(kicks);
+        all
+    }
+
+    fn kicks_180(
+        &self,
+        _tetromino: Tetromino,
+        from: State,
+        _to: State,
+        _origin: Pos,
+        _board: &Board,
+    ) -> Vec<(i32, i32)> {
+        srs_180_kicks(from)
+    }
+
+    fn final_kick(&self) -> Option<usize> {
+        Srs.final_kick()
+    }
+}
+
+/// The shapes used by both Sega rotation and ARS. Every piece sits at the
+/// bottom of its 3x3 box (or in the 2nd row of its 4x4 box, for `I`), with the
+/// origin at the centre.
+fn sega_shape(tetromino: Tetromino, rotation: State) -> [(i32, i32); 4] {
+    match tetromino {
+        Tetromino::I => match rotation {
+            State::Up | State::Down => [(-1, 0), (0, 0), (1, 0), (2, 0)],
+            State::Right | State::Left => [(1, -1), (1, 0), (1, 1), (1, 2)],
+        },
+        Tetromino::O => [(0, 0), (1, 0), (0, 1), (1, 1)],
+        Tetromino::T => match rotation {
+            State::Up => [(-1, 0), (0, 0), (1, 0), (0, 1)],
+            State::Right => [(0, -1), (-1, 0), (0, 0), (0, 1)],
+            State::Down => [(0, 0), (-1, 1), (0, 1), (1, 1)],
+            State::Left => [(0, -1), (0, 0), (1, 0), (0, 1)],
+        },
+        Tetromino::J => match rotation {
+            State::Up => [(-1, 0), (0, 0), (1, 0), (1, 1)],
+            State::Right => [(0, -1), (0, 0), (-1, 1), (0, 1)],
+            State::Down => [(-1, 0), (-1, 1), (0, 1), (1, 1)],
+            State::Left => [(0, -1), (1, -1), (0, 0), (0, 1)],
+        },
+        Tetromino::L => match rotation {
+            State::Up => [(-1, 0), (0, 0), (1, 0), (-1, 1)],
+            State::Right => [(-1, -1), (0, -1), (0, 0), (0, 1)],
+            State::Down => [(1, 0), (-1, 1), (0, 1), (1, 1)],
+            State::Left => [(0, -1), (0, 0), (0, 1), (1, 1)],
+        },
+        Tetromino::S => match rotation {
+            State::Up | State::Down => [(0, 0), (1, 0), (-1, 1), (0, 1)],
+            State::Right | State::Left => [(-1, -1), (-1, 0), (0, 0), (0, 1)],
+        },
+        Tetromino::Z => match rotation {
+            State::Up | State::Down => [(-1, 0), (0, 0), (0, 1), (1, 1)],
+            State::Right | State::Left => [(1, -1), (0, 0), (1, 0), (0, 1)],
+        },
+    }
+}
+
+/// The [Arika Rotation System](https://tetris.wiki/Arika_Rotation_System).
+/// It uses Sega's shapes, and when a rotation is blocked it tries one cell to
+/// the right, then one to the left. `I` pieces never kick, and `L`, `J` and `T`
+/// pieces don't kick when they're blocked in the centre column of their box.
+pub struct Ars;
+impl RotationSystem for Ars {
+    fn shape(&self, tetromino: Tetromino, rotation: State) -> [(i32, i32); 4] {
+        sega_shape(tetromino, rotation)
+    }
+
+    fn kicks(
+        &self,
+        tetromino: Tetromino,
+        _from: State,
+        to: State,
+        origin: Pos,
+        board: &Board,
+    ) -> Vec<(i32, i32)> {
+        match tetromino {
+            Tetromino::I | Tetromino::O => vec![(0, 0)],
+            Tetromino::L | Tetromino::J | Tetromino::T => {
+                // Finding the first blocked cell of the rotated piece, reading
+                // its box left to right, top to bottom.
+                let (row, col) = (origin.0 as i32, origin.1 as i32);
+                let mut cells = sega_shape(tetromino, to);
+                cells.sort_by_key(|&(x, y)| (y, x));
+                let first_blocked = cells
+                    .into_iter()
+                    .find(|&(x, y)| !board.is_empty_at(row + y, col + x));
+                match first_blocked {
+                    Some((0, _)) => vec![(0, 0)],
+                    _ => vec![(0, 0), (1, 0), (-1, 0)],
+                }
+            }
+            _ => vec![(0, 0), (1, 0), (-1, 0)],
+        }
+    }
+}
+
+/// The [Nintendo Rotation System](https://tetris.wiki/Nintendo_Rotation_System).
+/// Pieces turn about a single cell, and never kick.
+pub struct Nrs;
+impl RotationSystem for Nrs {
+    fn shape(&self, tetromino: Tetromino, rotation: State) -> [(i32, i32); 4] {
+        match tetromino {
+            Tetromino::I => match rotation {
+                State::Up | State::Down => [(-2, 0), (-1, 0), (0, 0), (1, 0)],
+                State::Right | State::Left => [(0, -2), (0, -1), (0, 0), (0, 1)],
+            },
+            Tetromino::O => [(-1, 0), (0, 0), (-1, 1), (0, 1)],
+            Tetromino::T => match rotation {
+                State::Up => [(-1, 0), (0, 0), (1, 0), (0, 1)],
+                State::Right => [(0, -1), (-1, 0), (0, 0), (0, 1)],
+                State::Down => [(0, -1), (-1, 0), (0, 0), (1, 0)],
+                State::Left => [(0, -1), (0, 0), (1, 0), (0, 1)],
+            },
+            Tetromino::J => match rotation {
+                State::Up => [(-1, 0), (0, 0), (1, 0), (1, 1)],
+                State::Right => [(0, -1), (0, 0), (-1, 1), (0, 1)],
+                State::Down => [(-1, -1), (-1, 0), (0, 0), (1, 0)],
+                State::Left => [(0, -1), (1, -1), (0, 0), (0, 1)],
+            },
+            Tetromino::L => match rotation {
+                State::Up => [(-1, 0), (0, 0), (1, 0), (-1, 1)],
+                State::Right => [(-1, -1), (0, -1), (0, 0), (0, 1)],
+                State::Down => [(1, -1), (-1, 0), (0, 0), (1, 0)],
+                State::Left => [(0, -1), (0, 0), (0, 1), (1, 1)],
+            },
+            Tetromino::S => match rotation {
+                State::Up | State::Down => [(0, 0), (1, 0), (-1, 1), (0, 1)],
+                State::Right | State::Left => [(0, -1), (0, 0), (1, 0), (1, 1)],
+            },
+            Tetromino::Z => match rotation {
+                State::Up | State::Down => [(-1, 0), (0, 0), (0, 1), (1, 1)],
+                State::Right | State::Left => [(1, -1), (0, 0), (1, 0), (0, 1)],
+            },
+        }
+    }
+
+    fn kicks(&self, _: Tetromino, _: State, _: State, _: Pos, _: &Board) -> Vec<(i32, i32)> {
+        vec![(0, 0)]
+    }
+}
+
+/// The rotation from [Sega's Tetris](https://tetris.wiki/Sega_Rotation). It's
+/// the system ARS grew out of; the same shapes, but without any kicks.
+pub struct Sega;
+impl RotationSystem for Sega {
+    fn shape(&self, tetromino: Tetromino, rotation: State) -> [(i32, i32); 4] {
+        sega_shape(tetromino, rotation)
+    }
+
+    fn kicks(&self, _: Tetromino, _: State, _: State, _: Pos, _: &Board) -> Vec<(i32, i32)> {
+        vec![(0, 0)]
+    }
+}
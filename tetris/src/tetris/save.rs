@@ -0,0 +1,118 @@
+use super::Tetris;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+/// The version of the save format written by this build. Saves from any other
+/// version are refused when loading.
+pub const SAVE_VERSION: u32 = 5;
+
+#[derive(Serialize)]
+struct SaveRef<'a> {
+    version: u32,
+    tetris: &'a Tetris,
+}
+
+#[derive(Deserialize)]
+struct Save {
+    version: u32,
+    tetris: Tetris,
+}
+
+impl Tetris {
+    /// Writes the entire state of the game out, so it can be resumed later.
+    pub fn write<W: Write>(&self, writer: W) -> io::Result<()> {
+        let save = SaveRef {
+            version: SAVE_VERSION,
+            tetris: self,
+        };
+        serde_json::to_writer(writer, &save)?;
+        Ok(())
+    }
+
+    /// Reads a game back in exactly as it was written, refusing any save from
+    /// a different version of the format.
+    pub fn read<R: Read>(reader: R) -> io::Result<Self> {
+        let save: Save = serde_json::from_reader(reader)?;
+        if save.version != SAVE_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "save is version {}, but only version {} is supported",
+                    save.version, SAVE_VERSION
+                ),
+            ));
+        }
+        if !save.tetris.active_fits() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "the active piece doesn't fit on the board",
+            ));
+        }
+        Ok(save.tetris)
+    }
+
+    /// If the active piece is on the board, and clear of the stack unless
+    /// it topped out there.
+    fn active_fits(&self) -> bool {
+        let on_board = self
+            .active
+            .get_squares()
+            .iter()
+            .all(|&(row, col)| self.board.in_range(row, col));
+        on_board && (self.is_game_over || !self.blocked(&self.active))
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write(&mut writer)?;
+        writer.flush()
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Tetris::read(BufReader::new(File::open(path)?))
+    }
+}
+
+/// The randomizer is saved through its `SavedRandomizer` snapshot.
+pub(super) mod randomizer {
+    use super::super::{Randomizer, SavedRandomizer};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    // Serde hands `with` functions a reference to the field itself.
+    #[allow(clippy::borrowed_box)]
+    pub fn serialize<S: Serializer>(
+        randomizer: &Box<dyn Randomizer>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        randomizer.save().serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Box<dyn Randomizer>, D::Error> {
+        Ok(SavedRandomizer::deserialize(deserializer)?.restore())
+    }
+}
+
+/// The scoring system is saved through its `SavedScoring` snapshot.
+pub(super) mod scoring {
+    use super::super::{SavedScoring, ScoringSystem};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    // Serde hands `with` functions a reference to the field itself.
+    #[allow(clippy::borrowed_box)]
+    pub fn serialize<S: Serializer>(
+        scoring: &Box<dyn ScoringSystem>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        scoring.save().serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Box<dyn ScoringSystem>, D::Error> {
+        Ok(SavedScoring::deserialize(deserializer)?.restore())
+    }
+}
@@ -0,0 +1,275 @@
+use super::SpinType;
+use serde::{Deserialize, Serialize};
+use strum_macros::{Display, EnumIter, EnumString};
+
+/// Everything about a piece locking that a scoring system might need. The
+/// engine works this out once per lock, and the `LinesCleared` event is made
+/// from the same values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Clear {
+    /// The number of rows cleared, which can be 0.
+    pub lines: u32,
+    pub spin: SpinType,
+    /// If this clear and the one before it were both difficult; a tetris or
+    /// a spin that cleared lines.
+    pub b2b: bool,
+    /// The number of back-to-backs in a row this clear makes, counting
+    /// itself, or 0 if it isn't one.
+    pub b2b_chain: u32,
+    /// The number of clears in a row before this one.
+    pub combo: u32,
+    /// If the clear left the board completely empty.
+    pub perfect_clear: bool,
+    /// The level the piece locked on.
+    pub level: u32,
+    /// The lines cleared in the game before this piece locked.
+    pub total_lines: u32,
+}
+
+/// A scoring system decides how many points every lock and drop is worth.
+pub trait ScoringSystem {
+    /// The points for a piece locking, whether or not it cleared anything.
+    fn clear(&mut self, clear: &Clear) -> u32;
+
+    /// The points for soft dropping the piece a number of rows.
+    fn soft_drop(&mut self, rows: u32, _level: u32) -> u32 {
+        rows
+    }
+
+    /// The points for hard dropping the piece a number of rows.
+    fn hard_drop(&mut self, rows: u32, _level: u32) -> u32 {
+        2 * rows
+    }
+
+    /// The grade a score is worth, for systems that hand them out.
+    fn grade(&self, _score: u32) -> Option<&'static str> {
+        None
+    }
+
+    /// A snapshot of the system's state, so it can be saved with the game.
+    fn save(&self) -> SavedScoring;
+}
+
+/// The state of any of the engine's scoring systems, in a form that can be
+/// written out along with a saved game.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum SavedScoring {
+    Guideline(GuidelineScoring),
+    Nes(NesScoring),
+    Sega(SegaScoring),
+    Tgm(TgmScoring),
+}
+impl SavedScoring {
+    /// Turns the saved state back into a working scoring system.
+    pub fn restore(self) -> Box<dyn ScoringSystem> {
+        match self {
+            SavedScoring::Guideline(s) => Box::new(s),
+            SavedScoring::Nes(s) => Box::new(s),
+            SavedScoring::Sega(s) => Box::new(s),
+            SavedScoring::Tgm(s) => Box::new(s),
+        }
+    }
+}
+
+/// The scoring systems that ship with the engine.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Default,
+    EnumIter,
+    EnumString,
+    Display,
+    Serialize,
+    Deserialize,
+)]
+#[strum(serialize_all = "lowercase")]
+pub enum ScoringKind {
+    /// Modern Guideline scoring, with spins, back-to-backs, combos and
+    /// perfect clears.
+    #[default]
+    Guideline,
+    /// The NES game's scoring.
+    Nes,
+    /// Sega's 1988 arcade scoring.
+    Sega,
+    /// Scoring from The Grand Master, which also gives out grades.
+    Tgm,
+}
+impl ScoringKind {
+    /// Creates a fresh scoring system of this kind.
+    pub fn build(self) -> Box<dyn ScoringSystem> {
+        match self {
+            ScoringKind::Guideline => Box::new(GuidelineScoring),
+            ScoringKind::Nes => Box::new(NesScoring),
+            ScoringKind::Sega => Box::new(SegaScoring),
+            ScoringKind::Tgm => Box::new(TgmScoring::default()),
+        }
+    }
+}
+
+/// [Guideline scoring](https://tetris.wiki/Scoring#Recent_guideline_compatible_games).
+/// Everything but drops is multiplied by the level, and difficult clears
+/// made back-to-back are worth half as much again.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GuidelineScoring;
+impl ScoringSystem for GuidelineScoring {
+    fn clear(&mut self, clear: &Clear) -> u32 {
+        let base = match (clear.lines, clear.spin) {
+            (0, SpinType::Not) => 0,
+            (0, SpinType::Mini(_)) => 100,
+            (0, SpinType::Full(_)) => 400,
+            (1, SpinType::Mini(_)) => 200,
+            (1, SpinType::Full(_)) => 800,
+            (1, _) => 100,
+            (2, SpinType::Mini(_)) => 400,
+            (2, SpinType::Full(_)) => 1200,
+            (2, _) => 300,
+            (3, SpinType::Full(_)) => 1600,
+            (3, _) => 500,
+            _ => 800,
+        };
+        let base = if clear.b2b { base * 3 / 2 } else { base };
+        let combo = if clear.lines > 0 { 50 * clear.combo } else { 0 };
+        let perfect_clear = match (clear.perfect_clear, clear.lines) {
+            (false, _) => 0,
+            (true, 4) if clear.b2b => 3200,
+            (true, 1) => 800,
+            (true, 2) => 1200,
+            (true, 3) => 1800,
+            (true, _) => 2000,
+        };
+        (base + combo + perfect_clear) * clear.level
+    }
+
+    fn save(&self) -> SavedScoring {
+        SavedScoring::Guideline(self.clone())
+    }
+}
+
+/// [NES scoring](https://tetris.wiki/Scoring#Original_Nintendo_scoring_system),
+/// with no points for spins or combos, and none for hard drops, which the NES
+/// didn't have.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NesScoring;
+impl ScoringSystem for NesScoring {
+    fn clear(&mut self, clear: &Clear) -> u32 {
+        let base = match clear.lines {
+            0 => 0,
+            1 => 40,
+            2 => 100,
+            3 => 300,
+            _ => 1200,
+        };
+        base * (clear.level + 1)
+    }
+
+    fn hard_drop(&mut self, _rows: u32, _level: u32) -> u32 {
+        0
+    }
+
+    fn save(&self) -> SavedScoring {
+        SavedScoring::Nes(self.clone())
+    }
+}
+
+/// Scoring from Sega's arcade game, where clears are worth more every two
+/// levels, up to five times as much, and dropping is worth nothing.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SegaScoring;
+impl ScoringSystem for SegaScoring {
+    fn clear(&mut self, clear: &Clear) -> u32 {
+        let base = match clear.lines {
+            0 => 0,
+            1 => 100,
+            2 => 400,
+            3 => 900,
+            _ => 2000,
+        };
+        base * (clear.level.saturating_sub(1) / 2 + 1).min(5)
+    }
+
+    fn soft_drop(&mut self, _rows: u32, _level: u32) -> u32 {
+        0
+    }
+
+    fn hard_drop(&mut self, _rows: u32, _level: u32) -> u32 {
+        0
+    }
+
+    fn save(&self) -> SavedScoring {
+        SavedScoring::Sega(self.clone())
+    }
+}
+
+/// The grades from The Grand Master, and the score needed for each.
+const TGM_GRADES: [(u32, &str); 18] = [
+    (0, "9"),
+    (400, "8"),
+    (800, "7"),
+    (1400, "6"),
+    (2000, "5"),
+    (3500, "4"),
+    (5500, "3"),
+    (8000, "2"),
+    (12000, "1"),
+    (16000, "S1"),
+    (22000, "S2"),
+    (30000, "S3"),
+    (40000, "S4"),
+    (52000, "S5"),
+    (66000, "S6"),
+    (82000, "S7"),
+    (100000, "S8"),
+    (120000, "S9"),
+];
+
+/// [TGM scoring](https://tetris.wiki/Tetris_The_Grand_Master#Scoring). TGM's
+/// level goes up with every line, so the lines cleared so far stand in for it.
+/// Soft drops are saved up and added to the next clear rather than scored on
+/// their own, and chaining clears grows a combo multiplier.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TgmScoring {
+    combo: u32,
+    soft: u32,
+}
+impl Default for TgmScoring {
+    fn default() -> Self {
+        TgmScoring { combo: 1, soft: 0 }
+    }
+}
+impl ScoringSystem for TgmScoring {
+    fn clear(&mut self, clear: &Clear) -> u32 {
+        let soft = std::mem::take(&mut self.soft);
+        if clear.lines == 0 {
+            self.combo = 1;
+            return 0;
+        }
+        self.combo += 2 * clear.lines - 2;
+        let bravo = if clear.perfect_clear { 4 } else { 1 };
+        ((clear.total_lines + clear.lines).div_ceil(4) + soft) * clear.lines * self.combo * bravo
+    }
+
+    fn soft_drop(&mut self, rows: u32, _level: u32) -> u32 {
+        self.soft += rows;
+        0
+    }
+
+    fn hard_drop(&mut self, _rows: u32, _level: u32) -> u32 {
+        0
+    }
+
+    fn grade(&self, score: u32) -> Option<&'static str> {
+        TGM_GRADES
+            .iter()
+            .rev()
+            .find(|&&(needed, _)| score >= needed)
+            .map(|&(_, grade)| grade)
+    }
+
+    fn save(&self) -> SavedScoring {
+        SavedScoring::Tgm(self.clone())
+    }
+}
@@ -0,0 +1,34 @@
+use serde::{Deserialize, Serialize};
+use strum_macros::{Display, EnumIter, EnumString};
+
+/// Which pieces can spin, and what their spins are worth.
+///
+/// `T` pieces always spin by the Guideline's 3-corner rule. Under the all-spin
+/// rules, the other pieces spin when they rotate into a spot they can't move
+/// left, right or up out of, as in Tetr.io. `O` pieces never spin.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Default,
+    EnumIter,
+    EnumString,
+    Display,
+    Serialize,
+    Deserialize,
+)]
+#[strum(serialize_all = "lowercase")]
+pub enum SpinRule {
+    /// Only `T` pieces spin.
+    #[default]
+    #[strum(serialize = "t-spin")]
+    TSpin,
+    /// Every piece spins, but spins other than T-spins only count as minis.
+    #[strum(serialize = "all-mini")]
+    AllMini,
+    /// Every piece spins, and spins other than T-spins count as full spins.
+    #[strum(serialize = "all-spin")]
+    AllSpin,
+}
@@ -0,0 +1,76 @@
+use super::{mode::FPS, Clear, SpinType, Tetromino};
+use serde::{Deserialize, Serialize};
+
+/// What's been done over a game, for seeing how fast and how well it's being
+/// played.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct Stats {
+    /// The frames the game has run for.
+    pub frames: u64,
+    /// The pieces locked onto the board.
+    pub pieces: u32,
+    /// The inputs made, counting a held key once.
+    pub keys: u32,
+    /// The lines of garbage sent, after cancelling.
+    pub attack: u32,
+    /// Singles, doubles, triples and tetrises made without a spin.
+    pub clears: [u32; 4],
+    /// T-spins that cleared no lines, one, two and three.
+    pub t_spins: [u32; 4],
+    /// Mini T-spins that cleared no lines, one and two.
+    pub t_spin_minis: [u32; 3],
+    /// Spins of any other piece that cleared lines.
+    pub other_spins: u32,
+    /// Clears that left the board empty.
+    pub perfect_clears: u32,
+    /// The longest combo, counting the clears after the first.
+    pub max_combo: u32,
+    /// The longest back-to-back chain, counting the difficult clears after
+    /// the first, as in [`Clear::b2b_chain`].
+    pub max_b2b: u32,
+}
+impl Stats {
+    /// Counts a piece locking with `clear`.
+    pub(super) fn record(&mut self, clear: &Clear) {
+        let lines = clear.lines as usize;
+        match clear.spin {
+            SpinType::Full(Tetromino::T) => self.t_spins[lines.min(3)] += 1,
+            SpinType::Mini(Tetromino::T) => self.t_spin_minis[lines.min(2)] += 1,
+            _ if lines == 0 => {}
+            SpinType::Not => self.clears[lines.min(4) - 1] += 1,
+            _ => self.other_spins += 1,
+        }
+        self.perfect_clears += clear.perfect_clear as u32;
+        self.max_combo = self.max_combo.max(clear.combo);
+        self.max_b2b = self.max_b2b.max(clear.b2b_chain);
+    }
+
+    /// The seconds the game has run for.
+    pub fn seconds(&self) -> f64 {
+        self.frames as f64 / FPS as f64
+    }
+
+    /// Pieces per second.
+    pub fn pps(&self) -> f64 {
+        match self.frames {
+            0 => 0.0,
+            _ => self.pieces as f64 / self.seconds(),
+        }
+    }
+
+    /// Attack per minute; lines of garbage sent each minute.
+    pub fn apm(&self) -> f64 {
+        match self.frames {
+            0 => 0.0,
+            _ => self.attack as f64 * 60.0 / self.seconds(),
+        }
+    }
+
+    /// Keys per piece.
+    pub fn kpp(&self) -> f64 {
+        match self.pieces {
+            0 => 0.0,
+            pieces => self.keys as f64 / pieces as f64,
+        }
+    }
+}
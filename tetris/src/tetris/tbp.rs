@@ -0,0 +1,513 @@
+use super::bot::place;
+use super::{Board, Bot, Config, Placement, SpinType, State, Tetris, Tetromino, GARBAGE_CELL};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::ffi::OsStr;
+use std::io::{self, BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use strum::IntoEnumIterator;
+
+/// What a frontend tells a bot, under the [Tetris Bot Protocol]. Messages go
+/// to the bot's stdin as JSON, one per line.
+///
+/// [Tetris Bot Protocol]: https://github.com/tetris-bot-protocol/tbp-spec
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FrontendMessage {
+    /// The rules of the game. The bot answers with [`BotMessage::Ready`] if it
+    /// can play by them, or [`BotMessage::Error`] if it can't.
+    Rules {},
+    /// Starts the bot thinking about a game.
+    Start(TbpStart),
+    /// Asks the bot for the moves it would make.
+    Suggest,
+    /// A move was made, so the bot can move on to the next piece.
+    Play {
+        #[serde(rename = "move")]
+        mv: TbpMove,
+    },
+    /// A new piece was added to the end of the queue.
+    NewPiece { piece: Tetromino },
+    /// The game the bot was thinking about is over.
+    Stop,
+    /// The bot should exit.
+    Quit,
+}
+
+/// What a bot tells its frontend, under the Tetris Bot Protocol. Messages come
+/// from the bot's stdout as JSON, one per line.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BotMessage {
+    /// Who the bot is, sent as soon as it starts.
+    Info {
+        name: String,
+        version: String,
+        author: String,
+        features: Vec<String>,
+    },
+    /// The bot can play by the rules it was given.
+    Ready,
+    /// The bot can't play by the rules it was given.
+    Error { reason: String },
+    /// The moves the bot would make, best first.
+    Suggestion { moves: Vec<TbpMove> },
+}
+
+/// A game for a bot to start thinking about.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TbpStart {
+    pub hold: Option<Tetromino>,
+    /// The active piece, then the pieces coming up after it.
+    pub queue: Vec<Tetromino>,
+    /// The number of clears in a row so far.
+    pub combo: u32,
+    /// If the last clear was a difficult one.
+    pub back_to_back: bool,
+    /// The board from the bottom row up. Each cell is empty, `G` for
+    /// garbage, or the letter of the piece that filled it.
+    pub board: Vec<Vec<Option<char>>>,
+}
+impl TbpStart {
+    /// The game as `tetris` stands.
+    pub fn from_tetris(tetris: &Tetris) -> Self {
+        let board = &tetris.board;
+        let mut queue = vec![tetris.active.tetromino];
+        queue.extend(tetris.queue.iter().copied());
+        TbpStart {
+            hold: tetris.held.0,
+            queue,
+            combo: (tetris.combo_count + 1).max(0) as u32,
+            back_to_back: tetris.b2b_chain.is_some(),
+            board: (0..board.height())
+                .rev()
+                .map(|row| board[row].iter().map(|&cell| cell_char(cell)).collect())
+                .collect(),
+        }
+    }
+
+    /// A game in this state to think about, played by `config`. The bottom
+    /// half of the board is visible, as with the Guideline's 40 rows. There's
+    /// no game without a piece to play, or on a board that
+    /// [`Board::from_rows`] won't take.
+    pub fn to_tetris(&self, config: Config) -> Option<Tetris> {
+        let (&active, queue) = self.queue.split_first()?;
+        let rows: Vec<Vec<u8>> = self
+            .board
+            .iter()
+            .rev()
+            .map(|row| row.iter().map(|&cell| cell_number(cell)).collect())
+            .collect();
+        let visible = rows.len().div_ceil(2);
+        let board = Board::from_rows(rows, visible).ok()?;
+        let mut tetris = Tetris::with_board(board, Some(active), config, None);
+        tetris.held = (self.hold, true);
+        tetris.queue = queue.iter().copied().collect();
+        tetris.combo_count = self.combo as i32 - 1;
+        tetris.b2b_chain = self.back_to_back.then_some(0);
+        Some(tetris)
+    }
+}
+
+/// How a cell of the board is written down for a bot.
+fn cell_char(cell: u8) -> Option<char> {
+    if cell == 0 {
+        return None;
+    }
+    let piece = Tetromino::iter().find(|&piece| piece as u8 == cell);
+    Some(piece.map_or('G', Tetromino::letter))
+}
+
+/// How a cell written down by a frontend is stored on the board.
+fn cell_number(cell: Option<char>) -> u8 {
+    match cell {
+        None => 0,
+        Some(letter) => Tetromino::iter()
+            .find(|piece| piece.letter() == letter)
+            .map_or(GARBAGE_CELL, |piece| piece as u8),
+    }
+}
+
+impl Tetromino {
+    /// The letter the piece is named for.
+    fn letter(self) -> char {
+        match self {
+            Tetromino::I => 'I',
+            Tetromino::O => 'O',
+            Tetromino::T => 'T',
+            Tetromino::J => 'J',
+            Tetromino::L => 'L',
+            Tetromino::S => 'S',
+            Tetromino::Z => 'Z',
+        }
+    }
+}
+
+/// The way a piece faces. Pieces spawn facing north.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Orientation {
+    North,
+    East,
+    South,
+    West,
+}
+impl From<State> for Orientation {
+    fn from(state: State) -> Self {
+        match state {
+            State::Up => Orientation::North,
+            State::Right => Orientation::East,
+            State::Down => Orientation::South,
+            State::Left => Orientation::West,
+        }
+    }
+}
+
+/// Where a piece is, by the cell at its centre, counting columns from the
+/// left and rows from the bottom.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PieceLocation {
+    #[serde(rename = "type")]
+    pub kind: Tetromino,
+    pub orientation: Orientation,
+    pub x: i32,
+    pub y: i32,
+}
+impl PieceLocation {
+    /// The cells the piece fills, as `(row, col)` from the top left of a
+    /// board `height` rows tall, sorted. Returns `None` if any are off the
+    /// top or the bottom of the board.
+    pub fn cells(&self, height: usize) -> Option<[(usize, usize); 4]> {
+        // Each piece's cells facing north, as (x, y) from its centre, which
+        // turn about the centre as it rotates.
+        let north = match self.kind {
+            Tetromino::I => [(-1, 0), (0, 0), (1, 0), (2, 0)],
+            Tetromino::O => [(0, 0), (1, 0), (0, 1), (1, 1)],
+            Tetromino::T => [(-1, 0), (0, 0), (1, 0), (0, 1)],
+            Tetromino::J => [(-1, 0), (0, 0), (1, 0), (-1, 1)],
+            Tetromino::L => [(-1, 0), (0, 0), (1, 0), (1, 1)],
+            Tetromino::S => [(-1, 0), (0, 0), (0, 1), (1, 1)],
+            Tetromino::Z => [(-1, 1), (0, 1), (0, 0), (1, 0)],
+        };
+        let mut cells = [(0, 0); 4];
+        for (cell, (x, y)) in cells.iter_mut().zip(north) {
+            let (x, y) = match self.orientation {
+                Orientation::North => (x, y),
+                Orientation::East => (y, -x),
+                Orientation::South => (-x, -y),
+                Orientation::West => (-y, x),
+            };
+            let row = height as i32 - 1 - (self.y + y);
+            if !(0..height as i32).contains(&row) || self.x + x < 0 {
+                return None;
+            }
+            *cell = (row as usize, (self.x + x) as usize);
+        }
+        cells.sort();
+        Some(cells)
+    }
+}
+
+/// How a move was spun into place.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TbpSpin {
+    None,
+    Mini,
+    Full,
+}
+
+/// Where a piece is put, and how it was spun there.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TbpMove {
+    pub location: PieceLocation,
+    pub spin: TbpSpin,
+}
+impl TbpMove {
+    /// The move that makes `placement` on a board `height` rows tall.
+    pub fn from_placement(placement: &Placement, height: usize) -> Self {
+        let facing = Orientation::from(placement.rotation);
+        // Pieces that look the same facing two ways are written the way the
+        // engine has them facing, if that's one of them.
+        let others = [
+            Orientation::North,
+            Orientation::East,
+            Orientation::South,
+            Orientation::West,
+        ]
+        .into_iter()
+        .filter(|&orientation| orientation != facing);
+        let location = [facing]
+            .into_iter()
+            .chain(others)
+            .flat_map(|orientation| {
+                // Every piece has a cell at its centre.
+                placement.cells.map(|(row, col)| PieceLocation {
+                    kind: placement.tetromino,
+                    orientation,
+                    x: col as i32,
+                    y: (height - 1 - row) as i32,
+                })
+            })
+            .find(|location| location.cells(height) == Some(placement.cells))
+            .expect("every placement fills one of its piece's shapes");
+        TbpMove {
+            location,
+            spin: match placement.spin {
+                SpinType::Not => TbpSpin::None,
+                SpinType::Mini(_) => TbpSpin::Mini,
+                SpinType::Full(_) => TbpSpin::Full,
+            },
+        }
+    }
+
+    /// The placement in `placements` that makes this move on `tetris`, if
+    /// there is one.
+    pub fn find<'a>(&self, tetris: &Tetris, placements: &'a [Placement]) -> Option<&'a Placement> {
+        let cells = self.location.cells(tetris.board.height())?;
+        // Moving a piece other than the active one means holding first.
+        let hold = self.location.kind != tetris.active.tetromino;
+        placements.iter().find(|placement| {
+            placement.cells == cells
+                && placement.hold == hold
+                && placement.tetromino == self.location.kind
+        })
+    }
+}
+
+/// Writes a message as a line of JSON.
+fn send<T: Serialize, W: Write>(writer: &mut W, message: &T) -> io::Result<()> {
+    let mut line = serde_json::to_vec(message)?;
+    line.push(b'\n');
+    writer.write_all(&line)?;
+    writer.flush()
+}
+
+/// Reads the next message from `reader`, skipping any line that isn't one,
+/// as the protocol asks.
+fn recv<T: DeserializeOwned, R: BufRead>(reader: &mut R) -> io::Result<T> {
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "the other side of the bot protocol closed",
+            ));
+        }
+        if let Ok(message) = serde_json::from_str(&line) {
+            return Ok(message);
+        }
+    }
+}
+
+/// What a bot knows about the game, to tell when it needs starting over.
+#[derive(Debug, PartialEq, Eq)]
+struct Known {
+    board: Board,
+    hold: Option<Tetromino>,
+    queue: Vec<Tetromino>,
+}
+
+/// A bot that speaks the Tetris Bot Protocol, such as Cold Clear or Zetris,
+/// playing a game through the engine.
+pub struct TbpBot<R: BufRead = BufReader<ChildStdout>, W: Write = ChildStdin> {
+    reader: R,
+    writer: W,
+    /// The bot's process, if we launched it.
+    child: Option<Child>,
+    name: String,
+    /// The game as the bot has it, once it's been started.
+    known: Option<Known>,
+}
+impl TbpBot {
+    /// Launches a bot from `program` with `args`, and waits for it to be
+    /// ready to play by the Guideline's rules.
+    pub fn launch<S: AsRef<OsStr>>(program: S, args: &[S]) -> io::Result<Self> {
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let reader = BufReader::new(child.stdout.take().expect("stdout is piped"));
+        let writer = child.stdin.take().expect("stdin is piped");
+        let mut bot = TbpBot::connect(reader, writer)?;
+        bot.child = Some(child);
+        Ok(bot)
+    }
+}
+impl<R: BufRead, W: Write> TbpBot<R, W> {
+    /// Speaks to a bot that's already running, reading what it says from
+    /// `reader` and telling it things through `writer`. Waits for the bot to
+    /// be ready to play.
+    pub fn connect(mut reader: R, mut writer: W) -> io::Result<Self> {
+        let name = loop {
+            if let BotMessage::Info { name, .. } = recv(&mut reader)? {
+                break name;
+            }
+        };
+        send(&mut writer, &FrontendMessage::Rules {})?;
+        loop {
+            match recv(&mut reader)? {
+                BotMessage::Ready => break,
+                BotMessage::Error { reason } => {
+                    return Err(io::Error::other(format!("{} won't play: {}", name, reason)))
+                }
+                _ => {}
+            }
+        }
+        Ok(TbpBot {
+            reader,
+            writer,
+            child: None,
+            name,
+            known: None,
+        })
+    }
+
+    /// The name the bot gave itself.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Asks the bot for a move, and makes it. The bot's moves are checked
+    /// against every placement the active piece has, and the first that can
+    /// be made is. Returns the placement made, if the bot suggested any that
+    /// could be.
+    ///
+    /// The bot's game is started the first time, and started over whenever
+    /// the game isn't what the bot expects, such as when garbage rises.
+    pub fn play(&mut self, tetris: &mut Tetris) -> io::Result<Option<Placement>> {
+        if tetris.is_game_over {
+            return Ok(None);
+        }
+        let known = Known {
+            board: tetris.board.clone(),
+            hold: tetris.held.0,
+            queue: TbpStart::from_tetris(tetris).queue,
+        };
+        if self.known.as_ref() != Some(&known) {
+            if self.known.is_some() {
+                send(&mut self.writer, &FrontendMessage::Stop)?;
+            }
+            send(
+                &mut self.writer,
+                &FrontendMessage::Start(TbpStart::from_tetris(tetris)),
+            )?;
+            self.known = Some(known);
+        }
+        send(&mut self.writer, &FrontendMessage::Suggest)?;
+        let moves = loop {
+            if let BotMessage::Suggestion { moves } = recv(&mut self.reader)? {
+                break moves;
+            }
+        };
+        let placements = tetris.placements();
+        let Some((mv, placement)) = moves.iter().find_map(|mv| {
+            mv.find(tetris, &placements)
+                .map(|placement| (*mv, placement.clone()))
+        }) else {
+            // The bot's lost track of the game, so it's started over next
+            // time.
+            send(&mut self.writer, &FrontendMessage::Stop)?;
+            self.known = None;
+            return Ok(None);
+        };
+        // Holding for the first time takes two pieces out of the queue.
+        let taken = 1 + (placement.hold && tetris.held.0.is_none()) as usize;
+        let (board, _) = place(&tetris.board, &placement);
+        for &action in &placement.inputs {
+            tetris.apply(action);
+        }
+        send(&mut self.writer, &FrontendMessage::Play { mv })?;
+        let queue = TbpStart::from_tetris(tetris).queue;
+        for &piece in &queue[queue.len() - taken..] {
+            send(&mut self.writer, &FrontendMessage::NewPiece { piece })?;
+        }
+        self.known = Some(Known {
+            board,
+            hold: tetris.held.0,
+            queue,
+        });
+        Ok(Some(placement))
+    }
+}
+impl<R: BufRead, W: Write> Drop for TbpBot<R, W> {
+    /// Tells the bot to quit, and makes sure it has if we launched it.
+    fn drop(&mut self) {
+        let _ = send(&mut self.writer, &FrontendMessage::Quit);
+        if let Some(child) = &mut self.child {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+/// Plays `bot` under the Tetris Bot Protocol, as an external bot would, until
+/// the frontend says to quit or goes away. What the frontend says is read
+/// from `reader`, and the bot's answers are written to `writer`; a bot
+/// process would use its stdin and stdout. The bot plays by the Guideline's
+/// rules.
+pub fn serve_tbp<R: BufRead, W: Write>(bot: &Bot, mut reader: R, mut writer: W) -> io::Result<()> {
+    send(
+        &mut writer,
+        &BotMessage::Info {
+            name: "tetroxide".to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            author: "tetroxide".to_string(),
+            features: Vec::new(),
+        },
+    )?;
+    let mut game: Option<Tetris> = None;
+    loop {
+        let message = match recv(&mut reader) {
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+            message => message?,
+        };
+        match message {
+            FrontendMessage::Rules {} => send(&mut writer, &BotMessage::Ready)?,
+            FrontendMessage::Start(start) => game = start.to_tetris(Config::default()),
+            FrontendMessage::Suggest => {
+                let moves = game
+                    .as_ref()
+                    .and_then(|tetris| {
+                        let placement = bot.choose(tetris)?;
+                        Some(TbpMove::from_placement(&placement, tetris.board.height()))
+                    })
+                    .into_iter()
+                    .collect();
+                send(&mut writer, &BotMessage::Suggestion { moves })?;
+            }
+            FrontendMessage::Play { mv } => {
+                let Some(tetris) = &mut game else {
+                    continue;
+                };
+                let placements = tetris.placements();
+                let Some(placement) = mv.find(tetris, &placements) else {
+                    // A move we can't make leaves us not knowing the game.
+                    game = None;
+                    continue;
+                };
+                let taken = 1 + (placement.hold && tetris.held.0.is_none()) as usize;
+                let known = tetris.queue.len();
+                if known < taken {
+                    // Nothing we know of comes out next.
+                    game = None;
+                    continue;
+                }
+                for &action in &placement.inputs {
+                    tetris.apply(action);
+                }
+                // Locking deals pieces from the game's own randomizer, which
+                // aren't the frontend's.
+                tetris.queue.truncate(known - taken);
+            }
+            FrontendMessage::NewPiece { piece } => {
+                if let Some(tetris) = &mut game {
+                    tetris.queue.push_back(piece);
+                }
+            }
+            FrontendMessage::Stop => game = None,
+            FrontendMessage::Quit => return Ok(()),
+        }
+    }
+}
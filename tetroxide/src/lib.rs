@@ -1,38 +1,112 @@
 pub mod tetroxide {
     use crossterm::{
-        event::{poll, read, Event, KeyCode, KeyEvent, KeyEventKind, ModifierKeyCode},
+        event::{
+            poll, read, Event, KeyCode, KeyEvent, KeyEventKind, KeyboardEnhancementFlags,
+            ModifierKeyCode, PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+        },
         execute,
-        terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen},
+        terminal::{
+            disable_raw_mode, enable_raw_mode, supports_keyboard_enhancement, EnterAlternateScreen,
+        },
         Result,
     };
     use spin_sleep::LoopHelper;
     use std::io::{self, Stdout};
+    use std::path::PathBuf;
     use std::time::Duration;
-    use tetris::tetris::{SpinType, Tetris};
+    use std::{env, fs};
+    use strum::IntoEnumIterator;
+    use tetris::tetris::{
+        format_time, Action, Bot, Config, GameEvent, GameModeKind, GameOverReason, Replay,
+        SpinType, TbpBot, Tetris,
+    };
     use tui::{
+        backend::Backend,
         backend::CrosstermBackend,
-        layout::{Alignment, Constraint, Direction, Layout},
+        layout::{Alignment, Constraint, Direction, Layout, Rect},
         style::{Color, Style},
         text::{Span, Spans, Text},
         widgets::{Block, BorderType, Borders, List, ListItem, ListState, Paragraph},
-        Terminal,
+        Frame, Terminal,
     };
 
+    mod online;
+    pub use online::Online;
+    mod versus;
+    pub use versus::Versus;
+
     #[derive(Debug, Clone, Copy)]
     enum MenuOpts {
         Restart,
         Quit,
         SetLevel(u32),
+        Resume,
+        Mode(GameModeKind),
     }
 
     #[derive(Debug, Clone, Copy)]
     enum MenuState {
         Pause,
         Level,
+        Resume,
+        Mode,
+    }
+
+    /// The options a game is started with. These persist across restarts.
+    #[derive(Debug, Clone, Default)]
+    pub struct Settings {
+        pub config: Config,
+        /// Where to save a replay of the game, if anywhere.
+        pub record: Option<PathBuf>,
+        /// Where to save an unfinished game when quitting, if anywhere.
+        pub save_file: Option<PathBuf>,
+        /// The bot that plays the game, if the player isn't.
+        pub autoplay: Option<Bot>,
+    }
+
+    /// The file an unfinished game is saved to by default; `.tetroxide-save.json`
+    /// in the user's home directory.
+    pub fn default_save_path() -> PathBuf {
+        env::var_os("HOME")
+            .or_else(|| env::var_os("USERPROFILE"))
+            .map(PathBuf::from)
+            .unwrap_or_default()
+            .join(".tetroxide-save.json")
+    }
+
+    /// The number of frames a callout like "TETRIS!" stays on screen for.
+    const CALLOUT_FRAMES: u64 = 120;
+
+    /// The number of frames between each piece the bot places.
+    const BOT_FRAMES: u64 = 15;
+
+    /// The columns the stats panel takes up beside a game.
+    const STATS_WIDTH: u16 = 22;
+
+    /// Text shown under the score after a notable clear.
+    #[derive(Debug, Clone)]
+    struct Callout {
+        text: String,
+        /// The frame it appeared on.
+        frame: u64,
+        /// What the clear was, if it emptied the board. It goes on a banner
+        /// over the board.
+        perfect_clear: Option<String>,
     }
 
     pub struct Game {
         tetris: Tetris,
+        settings: Settings,
+        callout: Option<Callout>,
+        /// How the game ended, once it has.
+        ending: Option<GameOverReason>,
+        /// An external bot that plays the game in place of the player, or the
+        /// built-in bot.
+        tbp_bot: Option<TbpBot>,
+        /// If the stats panel is showing beside the game.
+        show_stats: bool,
+        /// If the terminal reports key releases.
+        releases: bool,
     }
 
     impl Default for Game {
@@ -51,41 +125,467 @@ pub mod tetroxide {
             6 => Color::Green,
             7 => Color::Red,
             8 => Color::White,
+            9 => Color::Gray,
             _ => Color::Reset,
         };
         Style::default().fg(color)
     }
 
+    /// Puts the terminal into raw mode on the alternate screen, ready to draw.
+    fn enter_terminal() -> Result<Terminal<CrosstermBackend<Stdout>>> {
+        enable_raw_mode()?;
+        let mut stdout = io::stdout();
+        execute!(stdout, EnterAlternateScreen)?;
+        let backend = CrosstermBackend::new(stdout);
+        Terminal::new(backend)
+    }
+
+    /// The action a key is bound to, if any.
+    fn key_action(code: KeyCode) -> Option<Action> {
+        match code {
+            KeyCode::Char('a') | KeyCode::Left => Some(Action::ShiftLeft),
+            KeyCode::Char('d') | KeyCode::Right => Some(Action::ShiftRight),
+            KeyCode::Char('w') | KeyCode::Up => Some(Action::RotateCw),
+            KeyCode::Char('z')
+            | KeyCode::Modifier(ModifierKeyCode::LeftControl)
+            | KeyCode::Modifier(ModifierKeyCode::RightControl) => Some(Action::RotateCcw),
+            KeyCode::Char('x') => Some(Action::Rotate180),
+            KeyCode::Char('s') | KeyCode::Down => Some(Action::SoftDrop),
+            KeyCode::Char(' ') => Some(Action::HardDrop),
+            KeyCode::Char('c') => Some(Action::Hold),
+            _ => None,
+        }
+    }
+
+    /// Creates a game of tetris from the given settings.
+    fn new_tetris(settings: &Settings) -> Tetris {
+        let mut tetris = Tetris::with_config(settings.config, None);
+        if settings.record.is_some() {
+            tetris.record();
+        }
+        tetris
+    }
+
+    /// Updates a game's callout and ending from events taken off it.
+    fn show_events(
+        tetris: &Tetris,
+        events: &[GameEvent],
+        callout: &mut Option<Callout>,
+        ending: &mut Option<GameOverReason>,
+    ) {
+        let frame = tetris.get_frame();
+        for &event in events {
+            let text = match event {
+                GameEvent::GameOver { reason } => {
+                    *ending = Some(reason);
+                    continue;
+                }
+                GameEvent::LinesCleared { count: 4, .. } => "TETRIS!".to_string(),
+                GameEvent::LinesCleared {
+                    spin: SpinType::Full(tetromino),
+                    ..
+                }
+                | GameEvent::PieceLocked {
+                    spin: SpinType::Full(tetromino),
+                    ..
+                } => format!("{:?}-SPIN!", tetromino),
+                GameEvent::LinesCleared {
+                    spin: SpinType::Mini(tetromino),
+                    ..
+                }
+                | GameEvent::PieceLocked {
+                    spin: SpinType::Mini(tetromino),
+                    ..
+                } => format!("M. {:?}-SPIN!", tetromino),
+                GameEvent::LinesCleared { combo, .. } if combo > 0 => {
+                    format!("{}x COMBO", combo)
+                }
+                // Comes straight after its clear, so goes along with any
+                // callout that clear just made.
+                GameEvent::PerfectClear { lines, b2b } => {
+                    let name =
+                        ["SINGLE", "DOUBLE", "TRIPLE", "TETRIS"][lines.clamp(1, 4) as usize - 1];
+                    let banner = if b2b {
+                        format!("BACK-TO-BACK\n{}", name)
+                    } else {
+                        name.to_string()
+                    };
+                    match callout {
+                        Some(callout) if callout.frame == frame => {
+                            callout.perfect_clear = Some(banner);
+                        }
+                        _ => {
+                            *callout = Some(Callout {
+                                text: String::new(),
+                                frame,
+                                perfect_clear: Some(banner),
+                            })
+                        }
+                    }
+                    continue;
+                }
+                _ => continue,
+            };
+            let text = match (event, tetris.get_b2b_chain()) {
+                (GameEvent::LinesCleared { b2b: true, .. }, 1) => {
+                    format!("BACK-TO-BACK\n{}", text)
+                }
+                (GameEvent::LinesCleared { b2b: true, .. }, chain) => {
+                    format!("BACK-TO-BACK x{}\n{}", chain, text)
+                }
+                _ => text,
+            };
+            *callout = Some(Callout {
+                text,
+                frame,
+                perfect_clear: None,
+            });
+        }
+    }
+
+    /// Draws the visible part of the board, with its walls. The left wall
+    /// turns red from the bottom up to show the garbage waiting to rise.
+    fn draw_board(tetris: &Tetris) -> Text<'static> {
+        let mut text = Text::default();
+        let board = tetris.get_state();
+        let rows: Vec<_> = board.visible_rows().collect();
+        let pending = tetris.get_pending_garbage() as usize;
+        for (i, row) in rows.iter().enumerate() {
+            let s_vec: Vec<_> = row
+                .iter()
+                .map(|&x| {
+                    if x == 0 {
+                        Span::styled(" .", get_style(0))
+                    } else {
+                        Span::styled("[]", get_style(x))
+                    }
+                })
+                .collect();
+            let wall = if rows.len() - i <= pending {
+                Span::styled("<!", Style::default().fg(Color::Red))
+            } else {
+                Span::raw("<!")
+            };
+            let mut spans: Spans = Spans::from(vec![wall]);
+            spans.0.extend(s_vec);
+            spans.0.push(Span::raw("!>"));
+            text.extend(Text::from(spans));
+        }
+        text.extend(Text::from(format!("<!{}!>", "=".repeat(2 * board.width()))));
+        text.extend(Text::from("\\/".repeat(board.width())));
+        text
+    }
+
+    /// The columns a game takes up on screen. The board is 2 characters per
+    /// cell, plus its walls; the panels on either side of it are 12 wide.
+    fn player_width(tetris: &Tetris) -> u16 {
+        2 * tetris.get_config().width as u16 + 4 + 24
+    }
+
+    /// Draws a game, with its held piece, score and queue around it, into
+    /// `area`. Returns the area the board itself was drawn in, for menus to go
+    /// on top of.
+    ///
+    /// The finesse faults are only drawn when `presses` says each key press
+    /// reaches the game once. Terminals that don't report key releases send
+    /// their key repeats as more presses, which would make nearly every piece
+    /// a fault.
+    fn draw_player<B: Backend>(
+        f: &mut Frame<B>,
+        area: Rect,
+        tetris: &Tetris,
+        callout: &Option<Callout>,
+        title: &str,
+        presses: bool,
+    ) -> Rect {
+        let game_par = Paragraph::new(draw_board(tetris)).alignment(Alignment::Center);
+        let (held, h_tet) = tetris.get_held();
+        let held_par = Paragraph::new(Text::styled(held, get_style(h_tet)))
+            .alignment(Alignment::Center)
+            .block(
+                Block::default()
+                    .borders(Borders::all())
+                    .title("HELD")
+                    .title_alignment(Alignment::Center),
+            );
+        // displaying score/combo/tetris/spin
+        let mut score_text = format!("{}", tetris.score);
+        if let Some(grade) = tetris.get_grade() {
+            score_text += &format!("\nGRADE {}", grade);
+        }
+        let callout = callout
+            .as_ref()
+            .filter(|callout| tetris.get_frame() < callout.frame + CALLOUT_FRAMES);
+        if let Some(callout) = callout.filter(|callout| !callout.text.is_empty()) {
+            score_text += &format!("\n{}", callout.text);
+        }
+        // Room for the score, its grade and a callout.
+        let score_height = (score_text.lines().count() as u16 + 2).max(4);
+        let score_par = Paragraph::new(Text::from(score_text))
+            .alignment(Alignment::Center)
+            .block(
+                Block::default()
+                    .borders(Borders::all())
+                    .title("SCORE")
+                    .title_alignment(Alignment::Center),
+            );
+        // The game mode picks what goes under the score, like the level or
+        // the time left.
+        let mode = tetris.get_config().mode.mode();
+        let [top_par, bottom_par] = mode.hud(tetris).map(|(title, value)| {
+            Paragraph::new(Text::from(value))
+                .alignment(Alignment::Center)
+                .block(
+                    Block::default()
+                        .borders(Borders::all())
+                        .title(title)
+                        .title_alignment(Alignment::Center),
+                )
+        });
+        let mut next_text = Text::default();
+        for tet in tetris.get_queue() {
+            next_text.extend(Text::styled(tet.to_string(), get_style(tet as u8)));
+            next_text.extend(Text::raw("\n"));
+        }
+        let queue_par = Paragraph::new(next_text)
+            .alignment(Alignment::Center)
+            .block(
+                Block::default()
+                    .borders(Borders::all())
+                    .title("NEXT")
+                    .title_alignment(Alignment::Center),
+            );
+        // The finesse faults so far, flashing red for a moment after each.
+        let finesse = tetris.get_finesse();
+        let faulted = matches!(finesse.last_fault,
+            Some((_, frame)) if tetris.get_frame() < frame + CALLOUT_FRAMES);
+        let faults_style = if faulted {
+            Style::default().fg(Color::Red)
+        } else {
+            Style::default()
+        };
+        let faults_par = Paragraph::new(Text::styled(finesse.faults.to_string(), faults_style))
+            .alignment(Alignment::Center)
+            .block(
+                Block::default()
+                    .borders(Borders::all())
+                    .border_style(faults_style)
+                    .title("FAULTS")
+                    .title_alignment(Alignment::Center),
+            );
+        let game_block = Block::default()
+            .border_type(BorderType::Double)
+            .borders(Borders::ALL)
+            .title(title)
+            .title_alignment(Alignment::Center);
+
+        let board_width = 2 * tetris.get_config().width as u16 + 4;
+
+        let layout = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints(
+                [
+                    Constraint::Length(12),
+                    Constraint::Length(board_width),
+                    Constraint::Length(12),
+                    Constraint::Percentage(100),
+                ]
+                .as_ref(),
+            )
+            .margin(1)
+            .split(area);
+        let stats_layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(4),
+                Constraint::Length(score_height),
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Percentage(100),
+            ])
+            .split(layout[0]);
+        let next_layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(13),
+                Constraint::Length(3),
+                Constraint::Percentage(100),
+            ])
+            .split(layout[2]);
+        // Rendering all of our widgets.
+        f.render_widget(game_block, area);
+        f.render_widget(held_par, stats_layout[0]);
+        f.render_widget(score_par, stats_layout[1]);
+        f.render_widget(top_par, stats_layout[2]);
+        f.render_widget(bottom_par, stats_layout[3]);
+        f.render_widget(game_par, layout[1]);
+        f.render_widget(queue_par, next_layout[0]);
+        if presses {
+            f.render_widget(faults_par, next_layout[1]);
+        }
+        if let Some(clear) = callout.and_then(|callout| callout.perfect_clear.as_ref()) {
+            draw_banner(f, layout[1], "PERFECT CLEAR", clear, Color::Yellow);
+        }
+        layout[1]
+    }
+
+    /// Draws how fast and how well a game is being played into `area`. The
+    /// `full` summary adds a count of every kind of clear, for the end of a
+    /// game. The keys pressed and finesse are left out unless `presses`, as
+    /// with the faults in [`draw_player`].
+    fn draw_stats<B: Backend>(
+        f: &mut Frame<B>,
+        area: Rect,
+        tetris: &Tetris,
+        full: bool,
+        presses: bool,
+    ) {
+        let stats = tetris.get_stats();
+        let finesse = tetris.get_finesse();
+        let mut rows = vec![
+            ("TIME", format_time(stats.frames)),
+            ("PIECES", stats.pieces.to_string()),
+            ("PPS", format!("{:.2}", stats.pps())),
+            ("ATTACK", stats.attack.to_string()),
+            ("APM", format!("{:.1}", stats.apm())),
+        ];
+        if presses {
+            rows.extend([
+                ("KEYS", stats.keys.to_string()),
+                ("KPP", format!("{:.2}", stats.kpp())),
+                ("FAULTS", finesse.faults.to_string()),
+                ("FINESSE", format!("{:.1}%", finesse.accuracy())),
+            ]);
+        }
+        if full {
+            let [singles, doubles, triples, tetrises] = stats.clears;
+            let [t_spins, tss, tsd, tst] = stats.t_spins;
+            rows.extend([
+                ("", String::new()),
+                ("LINES", tetris.lines.to_string()),
+                ("SINGLES", singles.to_string()),
+                ("DOUBLES", doubles.to_string()),
+                ("TRIPLES", triples.to_string()),
+                ("TETRISES", tetrises.to_string()),
+                ("T-SPINS", t_spins.to_string()),
+                ("TSS", tss.to_string()),
+                ("TSD", tsd.to_string()),
+                ("TST", tst.to_string()),
+                ("MINIS", stats.t_spin_minis.iter().sum::<u32>().to_string()),
+                ("SPINS", stats.other_spins.to_string()),
+                ("PERFECT", stats.perfect_clears.to_string()),
+                ("MAX COMBO", stats.max_combo.to_string()),
+                ("MAX B2B", stats.max_b2b.to_string()),
+            ]);
+        }
+        // Labels down the left and values down the right, to fill the 18
+        // columns inside the panel's margin and borders.
+        let text: Vec<String> = rows
+            .into_iter()
+            .map(|(label, value)| format!("{:<10}{:>8}", label, value))
+            .collect();
+        let vert = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(text.len() as u16 + 2),
+                Constraint::Percentage(100),
+            ])
+            .margin(1)
+            .split(area);
+        let par = Paragraph::new(text.join("\n")).block(
+            Block::default()
+                .borders(Borders::all())
+                .title(if full { "SUMMARY" } else { "STATS" })
+                .title_alignment(Alignment::Center),
+        );
+        f.render_widget(par, vert[0]);
+    }
+
+    /// Draws a box with a title and a couple of lines of text over the top of
+    /// a board.
+    fn draw_banner<B: Backend>(
+        f: &mut Frame<B>,
+        board_area: Rect,
+        title: &str,
+        text: &str,
+        color: Color,
+    ) {
+        let vert = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(5),
+                Constraint::Length(text.lines().count() as u16 + 2),
+                Constraint::Percentage(100),
+            ])
+            .split(board_area);
+        let par = Paragraph::new(Text::from(text.to_string()))
+            .alignment(Alignment::Center)
+            .block(
+                Block::default()
+                    .border_type(BorderType::Thick)
+                    .borders(Borders::ALL)
+                    .title(title.to_string())
+                    .title_alignment(Alignment::Center)
+                    .style(Style::default().fg(color).bg(Color::Black)),
+            );
+        f.render_widget(par, vert[1]);
+    }
+
     impl Game {
         pub fn new() -> Self {
+            Self::with_settings(Settings::default())
+        }
+
+        pub fn with_settings(settings: Settings) -> Self {
             Game {
-                tetris: Tetris::default(),
+                tetris: new_tetris(&settings),
+                settings,
+                callout: None,
+                ending: None,
+                tbp_bot: None,
+                show_stats: false,
+                releases: false,
             }
         }
 
-        /// Helper function for drawing the game.
-        fn draw_game(&self) -> Text {
-            let mut text = Text::default();
-            let board = self.tetris.get_state();
-            for r in 0..20 {
-                let s_vec: Vec<_> = board[r + 20]
-                    .into_iter()
-                    .map(|x| {
-                        if x == 0 {
-                            Span::styled(" .", get_style(0))
-                        } else {
-                            Span::styled("[]", get_style(x))
-                        }
-                    })
-                    .collect();
-                let mut spans: Spans = Spans::from(vec![Span::raw("<!")]);
-                spans.0.extend(s_vec);
-                spans.0.push(Span::raw("!>"));
-                text.extend(Text::from(spans));
+        /// Hands the game over to an external bot.
+        pub fn set_tbp_bot(&mut self, bot: TbpBot) {
+            self.tbp_bot = Some(bot);
+        }
+
+        /// If a bot is playing, rather than the player.
+        fn autoplaying(&self) -> bool {
+            self.settings.autoplay.is_some() || self.tbp_bot.is_some()
+        }
+
+        /// If each key press reaches the game once, rather than again with
+        /// every key repeat. Bots press each key once.
+        fn counts_presses(&self) -> bool {
+            self.releases || self.autoplaying()
+        }
+
+        /// Starts a fresh game using the current settings, saving the replay
+        /// of the old one first.
+        fn restart(&mut self) -> Result<()> {
+            self.save_replay()?;
+            self.tetris = new_tetris(&self.settings);
+            self.callout = None;
+            self.ending = None;
+            Ok(())
+        }
+
+        /// Reacts to everything that's happened in the game since last time.
+        fn read_events(&mut self) {
+            let events = self.tetris.take_events();
+            show_events(&self.tetris, &events, &mut self.callout, &mut self.ending);
+        }
+
+        /// Writes the replay of the current game out, if we're recording.
+        fn save_replay(&mut self) -> Result<()> {
+            if let (Some(path), Some(replay)) = (&self.settings.record, self.tetris.take_replay()) {
+                replay.save(path)?;
             }
-            text.extend(Text::from(format!("<!{}!>", "=".repeat(20))));
-            text.extend(Text::from("\\/".repeat(10)));
-            text
+            Ok(())
         }
 
         /// Initiates and displays the level select menu.
@@ -138,11 +638,116 @@ pub mod tetroxide {
             Ok(())
         }
 
+        /// If there's a saved game, asks the player if they want to resume it.
+        /// The save is removed either way, as it's only good for one resume.
+        /// Returns `true` if the game was resumed.
+        async fn offer_resume(
+            &mut self,
+            terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+        ) -> Result<bool> {
+            let path = match &self.settings.save_file {
+                Some(path) if path.exists() => path.clone(),
+                _ => return Ok(false),
+            };
+            // A save that can't be read (say, from an older version) is just
+            // thrown away.
+            let saved = Tetris::load(&path);
+            fs::remove_file(&path)?;
+            let Ok(saved) = saved else {
+                return Ok(false);
+            };
+            let mut loop_helper = LoopHelper::builder().build_with_target_rate(60.0);
+            let mut menu_opt = MenuOpts::Resume;
+            loop {
+                loop_helper.loop_start();
+                self.render(terminal, Some((MenuState::Resume, menu_opt)))?;
+                let event_waiting = poll(Duration::from_secs(0))?;
+                let event = if event_waiting {
+                    read()?
+                } else {
+                    Event::FocusLost
+                };
+                if let Event::Key(KeyEvent {
+                    code,
+                    kind: KeyEventKind::Press,
+                    ..
+                }) = event
+                {
+                    match code {
+                        KeyCode::Up | KeyCode::Down => {
+                            menu_opt = match menu_opt {
+                                MenuOpts::Resume => MenuOpts::Restart,
+                                _ => MenuOpts::Resume,
+                            }
+                        }
+                        KeyCode::Enter => {
+                            let resume = matches!(menu_opt, MenuOpts::Resume);
+                            if resume {
+                                self.tetris = saved;
+                            }
+                            return Ok(resume);
+                        }
+                        KeyCode::Esc => return Ok(false),
+                        _ => {}
+                    }
+                }
+                loop_helper.loop_sleep();
+            }
+        }
+
+        /// The main menu, where the player picks the game mode to play. The
+        /// game is started over in that mode once one is picked.
+        async fn select_mode(
+            &mut self,
+            terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+        ) -> Result<()> {
+            let mut loop_helper = LoopHelper::builder().build_with_target_rate(60.0);
+            let modes: Vec<GameModeKind> = GameModeKind::iter().collect();
+            let mut selected = modes
+                .iter()
+                .position(|&mode| mode == self.settings.config.mode)
+                .unwrap_or(0);
+            loop {
+                loop_helper.loop_start();
+                self.render(
+                    terminal,
+                    Some((MenuState::Mode, MenuOpts::Mode(modes[selected]))),
+                )?;
+                let event_waiting = poll(Duration::from_secs(0))?;
+                let event = if event_waiting {
+                    read()?
+                } else {
+                    Event::FocusLost
+                };
+                if let Event::Key(KeyEvent {
+                    code,
+                    kind: KeyEventKind::Press,
+                    ..
+                }) = event
+                {
+                    match code {
+                        KeyCode::Up => selected = (selected + modes.len() - 1) % modes.len(),
+                        KeyCode::Down => selected = (selected + 1) % modes.len(),
+                        KeyCode::Enter => {
+                            self.settings.config.mode = modes[selected];
+                            self.tetris = new_tetris(&self.settings);
+                            return Ok(());
+                        }
+                        _ => {}
+                    }
+                }
+                loop_helper.loop_sleep();
+            }
+        }
+
         /// Pauses the game.
         async fn pause(
             &mut self,
             terminal: &mut Terminal<CrosstermBackend<Stdout>>,
         ) -> Result<Option<MenuOpts>> {
+            // The menu takes every key, releases included, so nothing can be
+            // left held once it's closed.
+            self.tetris.release_all();
             let mut loop_helper = LoopHelper::builder().build_with_target_rate(60.0);
             let mut menu_opt = MenuOpts::Restart;
             loop {
@@ -166,13 +771,13 @@ pub mod tetroxide {
                             }
                             KeyCode::Up => match menu_opt {
                                 MenuOpts::Quit => MenuOpts::SetLevel(0),
-                                MenuOpts::Restart => MenuOpts::Quit,
                                 MenuOpts::SetLevel(_) => MenuOpts::Restart,
+                                _ => MenuOpts::Quit,
                             },
                             KeyCode::Down => match menu_opt {
-                                MenuOpts::Quit => MenuOpts::Restart,
                                 MenuOpts::Restart => MenuOpts::SetLevel(0),
                                 MenuOpts::SetLevel(_) => MenuOpts::Quit,
+                                _ => MenuOpts::Restart,
                             },
                             KeyCode::Enter => {
                                 match menu_opt {
@@ -197,70 +802,15 @@ pub mod tetroxide {
             terminal: &mut Terminal<CrosstermBackend<Stdout>>,
             menu_data: Option<(MenuState, MenuOpts)>,
         ) -> Result<()> {
-            let game_par = Paragraph::new(self.draw_game()).alignment(Alignment::Center);
-            let (held, h_tet) = self.tetris.get_held();
-            let held_par = Paragraph::new(Text::styled(held, get_style(h_tet)))
-                .alignment(Alignment::Center)
-                .block(
-                    Block::default()
-                        .borders(Borders::all())
-                        .title("HELD")
-                        .title_alignment(Alignment::Center),
-                );
-            // displaying score/combo/tetris/spin
-            let score_text = if self.tetris.did_tetris {
-                format!("{}\nTETRIS!", self.tetris.score)
-            } else if let SpinType::Full = self.tetris.last_was_spin {
-                format!("{}\nT-SPIN!", self.tetris.score)
-            } else if let SpinType::Mini = self.tetris.last_was_spin {
-                format!("{}\nM. T-SPIN!", self.tetris.score)
-            } else if self.tetris.combo_count > 0 {
-                format!("{}\n{}x COMBO", self.tetris.score, self.tetris.combo_count)
+            // The summary takes the place of the stats panel once the game is
+            // over, whether or not the panel was showing.
+            let over = self.tetris.is_game_over;
+            let stats_width = if self.show_stats || over {
+                STATS_WIDTH
             } else {
-                format!("{}", self.tetris.score)
+                0
             };
-            let score_par = Paragraph::new(Text::from(score_text))
-                .alignment(Alignment::Center)
-                .block(
-                    Block::default()
-                        .borders(Borders::all())
-                        .title("SCORE")
-                        .title_alignment(Alignment::Center),
-                );
-            let level_par = Paragraph::new(Text::from(format!("{}", self.tetris.level)))
-                .alignment(Alignment::Center)
-                .block(
-                    Block::default()
-                        .borders(Borders::all())
-                        .title("LEVEL")
-                        .title_alignment(Alignment::Center),
-                );
-            let lines_par = Paragraph::new(Text::from(format!("{}", self.tetris.lines)))
-                .alignment(Alignment::Center)
-                .block(
-                    Block::default()
-                        .borders(Borders::all())
-                        .title("LINES")
-                        .title_alignment(Alignment::Center),
-                );
-            let mut next_text = Text::default();
-            for tet in self.tetris.get_queue() {
-                next_text.extend(Text::styled(tet.to_string(), get_style(tet as u8)));
-                next_text.extend(Text::raw("\n"));
-            }
-            let queue_par = Paragraph::new(next_text)
-                .alignment(Alignment::Center)
-                .block(
-                    Block::default()
-                        .borders(Borders::all())
-                        .title("NEXT")
-                        .title_alignment(Alignment::Center),
-                );
-            let game_block = Block::default()
-                .border_type(BorderType::Double)
-                .borders(Borders::ALL)
-                .title("TETROXIDE")
-                .title_alignment(Alignment::Center);
+            let total_width = player_width(&self.tetris) + stats_width;
 
             // DRAWING TO THE TERMINAL
             terminal.draw(|f| {
@@ -269,48 +819,36 @@ pub mod tetroxide {
                     .direction(Direction::Horizontal)
                     .constraints(
                         [
-                            Constraint::Length((size.width - 48) / 2),
-                            Constraint::Length(48),
-                            Constraint::Length((size.width - 48) / 2),
+                            Constraint::Length(size.width.saturating_sub(total_width) / 2),
+                            Constraint::Length(total_width),
+                            Constraint::Length(size.width.saturating_sub(total_width) / 2),
                         ]
                         .as_ref(),
                     )
                     .split(size);
-                let layout = Layout::default()
+                let title = match &self.tbp_bot {
+                    Some(bot) => bot.name().to_uppercase(),
+                    None if self.autoplaying() => "AUTOPLAY".to_string(),
+                    None => "TETROXIDE".to_string(),
+                };
+                let columns = Layout::default()
                     .direction(Direction::Horizontal)
-                    .constraints(
-                        [
-                            Constraint::Length(12),
-                            Constraint::Length(24),
-                            Constraint::Length(12),
-                            Constraint::Percentage(100),
-                        ]
-                        .as_ref(),
-                    )
-                    .margin(1)
-                    .split(all[1]);
-                let stats_layout = Layout::default()
-                    .direction(Direction::Vertical)
                     .constraints([
-                        Constraint::Length(4),
-                        Constraint::Length(4),
-                        Constraint::Length(3),
-                        Constraint::Length(3),
-                        Constraint::Percentage(100),
+                        Constraint::Length(total_width - stats_width),
+                        Constraint::Length(stats_width),
                     ])
-                    .split(layout[0]);
-                let next_layout = Layout::default()
-                    .direction(Direction::Vertical)
-                    .constraints([Constraint::Length(13), Constraint::Percentage(100)])
-                    .split(layout[2]);
-                // Rendering all of our widgets.
-                f.render_widget(game_block, all[1]);
-                f.render_widget(held_par, stats_layout[0]);
-                f.render_widget(score_par, stats_layout[1]);
-                f.render_widget(level_par, stats_layout[2]);
-                f.render_widget(lines_par, stats_layout[3]);
-                f.render_widget(game_par, layout[1]);
-                f.render_widget(queue_par, next_layout[0]);
+                    .split(all[1]);
+                let board_area = draw_player(
+                    f,
+                    columns[0],
+                    &self.tetris,
+                    &self.callout,
+                    &title,
+                    self.counts_presses(),
+                );
+                if stats_width > 0 {
+                    draw_stats(f, columns[1], &self.tetris, over, self.counts_presses());
+                }
                 if let Some((menu_state, menu_opt)) = menu_data {
                     match menu_state {
                         MenuState::Pause => {
@@ -321,7 +859,7 @@ pub mod tetroxide {
                                     Constraint::Length(5),
                                     Constraint::Percentage(100),
                                 ])
-                                .split(layout[1]);
+                                .split(board_area);
                             let pause_layout = Layout::default()
                                 .direction(Direction::Horizontal)
                                 .constraints([
@@ -335,10 +873,11 @@ pub mod tetroxide {
                                 ListItem::new("Set Level "),
                                 ListItem::new("Quit      "),
                             ];
-                            let title = if self.tetris.is_game_over {
-                                "GAME OVER"
-                            } else {
-                                "PAUSE"
+                            let title = match self.ending {
+                                Some(GameOverReason::Finished) => "FINISHED",
+                                Some(GameOverReason::TimeUp) => "TIME UP",
+                                _ if self.tetris.is_game_over => "GAME OVER",
+                                _ => "PAUSE",
                             };
                             let pause_list = List::new(items)
                                 .block(
@@ -358,6 +897,77 @@ pub mod tetroxide {
                             state.select(Some(idx));
                             f.render_stateful_widget(pause_list, pause_layout[1], &mut state);
                         }
+                        MenuState::Resume => {
+                            let resume_vert = Layout::default()
+                                .direction(Direction::Vertical)
+                                .constraints([
+                                    Constraint::Length(5),
+                                    Constraint::Length(4),
+                                    Constraint::Percentage(100),
+                                ])
+                                .split(board_area);
+                            let resume_layout = Layout::default()
+                                .direction(Direction::Horizontal)
+                                .constraints([
+                                    Constraint::Percentage(26),
+                                    Constraint::Length(12),
+                                    Constraint::Percentage(100),
+                                ])
+                                .split(resume_vert[1]);
+                            let items = [ListItem::new("Resume    "), ListItem::new("New Game  ")];
+                            let resume_list = List::new(items)
+                                .block(
+                                    Block::default()
+                                        .border_type(BorderType::Thick)
+                                        .borders(Borders::ALL)
+                                        .title("SAVED GAME"),
+                                )
+                                .highlight_style(Style::default().fg(Color::Black).bg(Color::White))
+                                .style(Style::default().fg(Color::White).bg(Color::Black));
+                            let mut state = ListState::default();
+                            state.select(Some(match menu_opt {
+                                MenuOpts::Resume => 0,
+                                _ => 1,
+                            }));
+                            f.render_stateful_widget(resume_list, resume_layout[1], &mut state);
+                        }
+                        MenuState::Mode => {
+                            let modes: Vec<GameModeKind> = GameModeKind::iter().collect();
+                            let mode_vert = Layout::default()
+                                .direction(Direction::Vertical)
+                                .constraints([
+                                    Constraint::Length(5),
+                                    Constraint::Length(modes.len() as u16 + 2),
+                                    Constraint::Percentage(100),
+                                ])
+                                .split(board_area);
+                            let mode_layout = Layout::default()
+                                .direction(Direction::Horizontal)
+                                .constraints([
+                                    Constraint::Percentage(26),
+                                    Constraint::Length(12),
+                                    Constraint::Percentage(100),
+                                ])
+                                .split(mode_vert[1]);
+                            let items: Vec<ListItem> = modes
+                                .iter()
+                                .map(|mode| ListItem::new(format!("{:10}", mode.mode().name())))
+                                .collect();
+                            let mode_list = List::new(items)
+                                .block(
+                                    Block::default()
+                                        .border_type(BorderType::Thick)
+                                        .borders(Borders::ALL)
+                                        .title("MODE"),
+                                )
+                                .highlight_style(Style::default().fg(Color::Black).bg(Color::White))
+                                .style(Style::default().fg(Color::White).bg(Color::Black));
+                            let mut state = ListState::default();
+                            if let MenuOpts::Mode(mode) = menu_opt {
+                                state.select(modes.iter().position(|&m| m == mode));
+                            }
+                            f.render_stateful_widget(mode_list, mode_layout[1], &mut state);
+                        }
                         MenuState::Level => {
                             let lvl_vert = Layout::default()
                                 .direction(Direction::Vertical)
@@ -366,7 +976,7 @@ pub mod tetroxide {
                                     Constraint::Length(3),
                                     Constraint::Percentage(100),
                                 ])
-                                .split(layout[1]);
+                                .split(board_area);
                             let lvl_layout = Layout::default()
                                 .direction(Direction::Horizontal)
                                 .constraints([
@@ -397,74 +1007,173 @@ pub mod tetroxide {
         }
 
         /// Core game loop; runs and takes user inputs, and exits when closed from the menu.
+        /// When `watching`, games start over on their own a couple of seconds
+        /// after they end, and only `Esc` does anything.
         async fn game_loop(
             &mut self,
             terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+            watching: bool,
         ) -> Result<()> {
             let mut loop_helper = LoopHelper::builder().build_with_target_rate(60.0); // limit to 60 FPS if possible
-            loop {
+
+            // Terminals that report key releases let the engine handle DAS and
+            // ARR, while the rest fall back on the terminal's own key repeat.
+            self.releases = supports_keyboard_enhancement().unwrap_or(false);
+            if self.releases {
+                execute!(
+                    io::stdout(),
+                    PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::REPORT_EVENT_TYPES)
+                )?;
+            }
+            let mut over_for = 0;
+            'game: loop {
                 loop_helper.loop_start();
                 self.render(terminal, None)?;
-                if self.tetris.is_game_over {
+                if self.tetris.is_game_over && watching {
+                    over_for += 1;
+                    if over_for >= 2 * CALLOUT_FRAMES {
+                        over_for = 0;
+                        self.restart()?;
+                    }
+                    // The finished game stays on screen as it ended, without
+                    // running on, and only `Esc` does anything.
+                    while poll(Duration::from_secs(0))? {
+                        if let Event::Key(KeyEvent {
+                            code: KeyCode::Esc,
+                            kind: KeyEventKind::Press,
+                            ..
+                        }) = read()?
+                        {
+                            break 'game;
+                        }
+                    }
+                    loop_helper.loop_sleep();
+                    continue;
+                } else if self.tetris.is_game_over {
                     match self.pause(terminal).await? {
                         Some(MenuOpts::Restart) => {
-                            self.tetris = Tetris::default();
+                            self.restart()?;
                             continue;
                         }
                         Some(MenuOpts::Quit) => break,
                         _ => {}
                     }
                 }
-                let event_waiting = poll(Duration::from_secs(0))?;
-                let event = if event_waiting {
-                    read()?
-                } else {
-                    Event::FocusLost
-                };
-                if let Event::Key(KeyEvent {
-                    code,
-                    kind: KeyEventKind::Press,
-                    ..
-                }) = event
-                {
-                    match code {
-                        KeyCode::Esc => match self.pause(terminal).await? {
+                let mut events = Vec::new();
+                while poll(Duration::from_secs(0))? {
+                    events.push(read()?);
+                }
+                for event in events {
+                    let Event::Key(KeyEvent { code, kind, .. }) = event else {
+                        continue;
+                    };
+                    if code == KeyCode::Tab && kind == KeyEventKind::Press {
+                        self.show_stats = !self.show_stats;
+                    } else if code == KeyCode::Esc && kind == KeyEventKind::Press && watching {
+                        break 'game;
+                    } else if code == KeyCode::Esc && kind == KeyEventKind::Press {
+                        match self.pause(terminal).await? {
                             Some(MenuOpts::Restart) => {
-                                self.tetris = Tetris::default();
-                                continue;
+                                self.restart()?;
+                                continue 'game;
+                            }
+                            Some(MenuOpts::Quit) => {
+                                // Quitting mid-game saves it, so it can be
+                                // picked back up next time.
+                                if let Some(path) = &self.settings.save_file {
+                                    self.tetris.save(path)?;
+                                }
+                                break 'game;
                             }
-                            Some(MenuOpts::Quit) => break,
                             Some(MenuOpts::SetLevel(_)) => self.level_select(terminal).await?,
-                            None => {}
-                        },
-                        KeyCode::Char('a') | KeyCode::Left => self.tetris.shift(true),
-                        KeyCode::Char('d') | KeyCode::Right => self.tetris.shift(false),
-                        KeyCode::Char('w') | KeyCode::Up => self.tetris.rotate(true),
-                        KeyCode::Char('z')
-                        | KeyCode::Modifier(ModifierKeyCode::LeftControl)
-                        | KeyCode::Modifier(ModifierKeyCode::RightControl) => {
-                            self.tetris.rotate(false)
+                            _ => {}
                         }
-                        KeyCode::Char('s') | KeyCode::Down => self.tetris.soft_drop(),
-                        KeyCode::Char(' ') => self.tetris.hard_drop(),
-                        KeyCode::Char('c') => self.tetris.hold(),
-                        _ => {}
+                    } else if self.autoplaying() {
+                        continue;
+                    } else if let Some(action) = key_action(code) {
+                        match kind {
+                            KeyEventKind::Press if self.releases => {
+                                self.tetris.set_held(action, true)
+                            }
+                            KeyEventKind::Release => self.tetris.set_held(action, false),
+                            KeyEventKind::Press => {
+                                self.tetris.apply(action);
+                            }
+                            KeyEventKind::Repeat => {}
+                        }
+                    }
+                }
+                if !self.tetris.is_game_over && self.tetris.get_frame().is_multiple_of(BOT_FRAMES) {
+                    if let Some(bot) = &mut self.tbp_bot {
+                        bot.play(&mut self.tetris)?;
+                    } else if let Some(bot) = &self.settings.autoplay {
+                        bot.play(&mut self.tetris);
                     }
                 }
                 self.tetris.frame_advance();
+                self.read_events();
+                loop_helper.loop_sleep();
+            }
+            if self.releases {
+                execute!(io::stdout(), PopKeyboardEnhancementFlags)?;
+            }
+            self.save_replay()
+        }
+
+        /// Plays a replay back, exiting when it ends or when `Esc` is pressed.
+        async fn replay_loop(
+            &mut self,
+            terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+            replay: &Replay,
+        ) -> Result<()> {
+            let mut loop_helper = LoopHelper::builder().build_with_target_rate(60.0);
+            self.tetris = replay.new_game();
+            let mut playback = replay.playback();
+            while !playback.is_finished(&self.tetris) {
+                loop_helper.loop_start();
+                self.render(terminal, None)?;
+                if poll(Duration::from_secs(0))? {
+                    if let Event::Key(KeyEvent {
+                        code: KeyCode::Esc,
+                        kind: KeyEventKind::Press,
+                        ..
+                    }) = read()?
+                    {
+                        break;
+                    }
+                }
+                playback.step(&mut self.tetris);
+                self.read_events();
                 loop_helper.loop_sleep();
             }
             Ok(())
         }
 
         pub async fn run(&mut self) -> Result<()> {
-            enable_raw_mode()?;
-            let mut stdout = io::stdout();
-            execute!(stdout, EnterAlternateScreen)?;
-            let backend = CrosstermBackend::new(stdout);
-            let mut terminal = Terminal::new(backend)?;
+            let mut terminal = enter_terminal()?;
+            if !self.offer_resume(&mut terminal).await? {
+                self.select_mode(&mut terminal).await?;
+            }
             // Main game event loop
-            self.game_loop(&mut terminal).await?;
+            self.game_loop(&mut terminal, false).await?;
+            disable_raw_mode()?;
+            Ok(())
+        }
+
+        /// Watches the bot play game after game, until `Esc` is pressed. An
+        /// external bot, if there is one, plays in place of `bot`.
+        pub async fn watch_bot(&mut self, bot: Bot) -> Result<()> {
+            self.settings.autoplay = Some(bot);
+            let mut terminal = enter_terminal()?;
+            self.game_loop(&mut terminal, true).await?;
+            disable_raw_mode()?;
+            Ok(())
+        }
+
+        /// Watches a replay in the terminal.
+        pub async fn watch(&mut self, replay: &Replay) -> Result<()> {
+            let mut terminal = enter_terminal()?;
+            self.replay_loop(&mut terminal, replay).await?;
             disable_raw_mode()?;
             Ok(())
         }
@@ -1,27 +1,197 @@
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use futures::executor::block_on;
 use std::io::{self, BufRead};
-use tetris::tetris::Tetris;
-use tetroxide::tetroxide::Game;
+use std::path::PathBuf;
+use tetris::tetris::{
+    serve_tbp, Action, AttackKind, Bot, Config, GameModeKind, Handling, LockReset, RandomizerKind,
+    Replay, RotationSystemKind, ScoringKind, Server, SpinRule, TbpBot, Tetris, DEFAULT_LOCK_DELAY,
+};
+use tetroxide::tetroxide::{default_save_path, Game, Online, Settings, Versus};
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
     #[arg(short, long, default_value_t = false)]
     debug: bool,
+    /// The piece randomizer to use: 7bag, 14bag, random, tgm or nes.
+    #[arg(short, long, default_value_t = RandomizerKind::SevenBag)]
+    randomizer: RandomizerKind,
+    /// The rotation system to use: srs, srs+, ars, nrs or sega.
+    #[arg(long, default_value_t = RotationSystemKind::Srs)]
+    rotation: RotationSystemKind,
+    /// The number of columns on the board.
+    #[arg(long, default_value_t = 10, value_parser = clap::value_parser!(u16).range(4..=40))]
+    width: u16,
+    /// The number of visible rows on the board.
+    #[arg(long, default_value_t = 20, value_parser = clap::value_parser!(u16).range(4..=40))]
+    height: u16,
+    /// The scoring system to use: guideline, nes, sega or tgm.
+    #[arg(long, default_value_t = ScoringKind::Guideline)]
+    scoring: ScoringKind,
+    /// The game mode to start on the menu with: endless, marathon, sprint,
+    /// ultra or zen.
+    #[arg(long, default_value_t = GameModeKind::Endless)]
+    mode: GameModeKind,
+    /// Which pieces can spin: t-spin, all-mini or all-spin.
+    #[arg(long, default_value_t = SpinRule::TSpin)]
+    spins: SpinRule,
+    /// How much garbage clears send in versus: guideline or tetrio.
+    #[arg(long, default_value_t = AttackKind::Guideline)]
+    attack: AttackKind,
+    /// What restarts the lock delay of a resting piece: move, step or infinite.
+    #[arg(long, default_value_t = LockReset::Move)]
+    lock_reset: LockReset,
+    /// The number of frames a piece can rest on the stack before it locks,
+    /// as a comma-separated list for levels 1 to 15. The last value carries
+    /// on through the levels after it [default: 30]
+    #[arg(long, value_parser = parse_lock_delay)]
+    lock_delay: Option<[u8; 15]>,
+    /// Frames a direction is held before the piece starts moving on its own.
+    #[arg(long, default_value_t = Handling::default().das)]
+    das: u8,
+    /// Frames between each automatic shift; 0 goes straight to the wall.
+    #[arg(long, default_value_t = Handling::default().arr)]
+    arr: u8,
+    /// How many times faster than gravity soft drop is; 0 goes straight down.
+    #[arg(long, default_value_t = Handling::default().sdf)]
+    sdf: u8,
+    /// Play a match against another player through the server at this
+    /// address, such as `localhost:7878`.
+    #[arg(long)]
+    connect: Option<String>,
+    /// Let the bot play the game.
+    #[arg(long, default_value_t = false)]
+    autoplay: bool,
+    /// How many pieces of the queue the bot looks at past the active one.
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u8).range(0..=4))]
+    lookahead: u8,
+    /// Let an external bot that speaks the Tetris Bot Protocol play the game,
+    /// launched with this command, such as `cold-clear`.
+    #[arg(long)]
+    tbp: Option<String>,
+    /// Save a replay of the game to this file.
+    #[arg(long)]
+    record: Option<PathBuf>,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Watch a replay saved with `--record`.
+    Replay { file: PathBuf },
+    /// Watch the bot play, starting over whenever it tops out.
+    Bot,
+    /// Serve the built-in bot over the Tetris Bot Protocol on stdin and
+    /// stdout, for other frontends to play with.
+    Tbp,
+    /// Play a local two-player match, side by side on one keyboard.
+    Versus,
+    /// Run a server that pairs up players who `--connect` to it, and referees
+    /// their matches. Matches are played with the rest of the options given.
+    Server {
+        /// The address to listen on.
+        #[arg(default_value = "0.0.0.0:7878")]
+        address: String,
+    },
+}
+
+/// Reads the lock delays given with `--lock-delay`, one for each level from
+/// 1, with the last one filling in the levels after it.
+fn parse_lock_delay(text: &str) -> Result<[u8; 15], String> {
+    let delays = text
+        .split(',')
+        .map(|frames| {
+            frames
+                .trim()
+                .parse::<u8>()
+                .map_err(|_| format!("`{}` isn't a number of frames from 0 to 255", frames))
+        })
+        .collect::<Result<Vec<u8>, String>>()?;
+    if delays.len() > 15 {
+        return Err(format!(
+            "there are 15 levels, but {} delays were given",
+            delays.len()
+        ));
+    }
+    // Splitting always gives at least one value.
+    let mut table = [delays[delays.len() - 1]; 15];
+    table[..delays.len()].copy_from_slice(&delays);
+    Ok(table)
+}
+
+/// Launches the external bot given with `--tbp`, if there is one.
+fn launch_tbp(command: &Option<String>) -> io::Result<Option<TbpBot>> {
+    let Some(command) = command else {
+        return Ok(None);
+    };
+    let mut words = command.split_whitespace();
+    let program = words.next().unwrap_or_default();
+    let args: Vec<&str> = words.collect();
+    TbpBot::launch(program, &args).map(Some)
 }
 
 fn main() -> Result<(), std::io::Error> {
     let args = Args::parse();
-    if args.debug {
+    let config = Config {
+        randomizer: args.randomizer,
+        rotation: args.rotation,
+        width: args.width as usize,
+        height: args.height as usize,
+        lock_reset: args.lock_reset,
+        lock_delay: args.lock_delay.unwrap_or([DEFAULT_LOCK_DELAY; 15]),
+        handling: Handling {
+            das: args.das,
+            arr: args.arr,
+            sdf: args.sdf,
+        },
+        scoring: args.scoring,
+        spins: args.spins,
+        mode: args.mode,
+        attack: args.attack,
+        ..Config::default()
+    };
+    if let Some(Command::Replay { file }) = args.command {
+        let replay = Replay::load(file)?;
+        block_on(Game::new().watch(&replay))?;
+    } else if let Some(Command::Bot) = args.command {
+        let mut game = Game::with_settings(Settings {
+            config,
+            record: args.record,
+            ..Settings::default()
+        });
+        if let Some(bot) = launch_tbp(&args.tbp)? {
+            game.set_tbp_bot(bot);
+        }
+        block_on(game.watch_bot(Bot::new(args.lookahead as usize)))?;
+    } else if let Some(Command::Tbp) = args.command {
+        let stdin = io::stdin();
+        serve_tbp(
+            &Bot::new(args.lookahead as usize),
+            stdin.lock(),
+            io::stdout().lock(),
+        )?;
+    } else if let Some(Command::Versus) = args.command {
+        block_on(Versus::new(config).run())?;
+    } else if let Some(Command::Server { address }) = args.command {
+        let server = Server::bind(&address, config)?;
+        println!("Listening on {}", server.local_addr()?);
+        server.serve()?;
+    } else if let Some(address) = args.connect {
+        println!("Waiting for an opponent on {}...", address);
+        block_on(Online::connect(&address)?.run())?;
+    } else if args.debug {
         print!("\x1B[2J\x1B[1;1H");
-        let mut tet = Tetris::default();
+        let mut tet = Tetris::with_config(config, None);
+        if args.record.is_some() {
+            tet.record();
+        }
         tet.set_level(13);
         // We can rotate and not drop for a limited amount of time in
         // debug mode.
         println!("{}", tet);
         while !tet.is_game_over {
-            println!("Input: (w - hold | q & e - rotate | a & d - shift | s - soft drop | z - hard drop)");
+            println!("Input: (w - hold | q & e - rotate | r - rotate 180 | a & d - shift | s - soft drop | z - hard drop)");
             let mut buffer = String::new();
             let stdin = io::stdin();
             let mut handle = stdin.lock();
@@ -29,21 +199,37 @@ fn main() -> Result<(), std::io::Error> {
             print!("\x1B[2J\x1B[1;1H");
             let lower = buffer.to_lowercase();
             let trimmed = lower.trim();
-            match trimmed {
-                "w" => tet.hold(),
-                "q" => tet.rotate(true),
-                "e" => tet.rotate(false),
-                "a" => tet.shift(true),
-                "d" => tet.shift(false),
-                "s" => tet.soft_drop(),
-                "z" => tet.hard_drop(),
-                _ => {}
-            }
+            let action = match trimmed {
+                "w" => Some(Action::Hold),
+                "q" => Some(Action::RotateCw),
+                "e" => Some(Action::RotateCcw),
+                "r" => Some(Action::Rotate180),
+                "a" => Some(Action::ShiftLeft),
+                "d" => Some(Action::ShiftRight),
+                "s" => Some(Action::SoftDrop),
+                "z" => Some(Action::HardDrop),
+                _ => None,
+            };
+            let outcome = action.map(|action| tet.apply(action));
             tet.frame_advance();
             println!("{}", tet);
+            if let Some(outcome) = outcome {
+                println!("{:?}", outcome);
+            }
+        }
+        if let (Some(path), Some(replay)) = (args.record, tet.take_replay()) {
+            replay.save(path)?;
         }
     } else {
-        let mut game = Game::new();
+        let mut game = Game::with_settings(Settings {
+            config,
+            record: args.record,
+            save_file: Some(default_save_path()),
+            autoplay: args.autoplay.then(|| Bot::new(args.lookahead as usize)),
+        });
+        if let Some(bot) = launch_tbp(&args.tbp)? {
+            game.set_tbp_bot(bot);
+        }
         block_on(game.run())?;
     }
     Ok(())
@@ -0,0 +1,152 @@
+use super::{
+    draw_banner, draw_player, enter_terminal, key_action, player_width, show_events, Callout,
+};
+use crossterm::{
+    event::{
+        poll, read, Event, KeyCode, KeyEvent, KeyEventKind, KeyboardEnhancementFlags,
+        PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+    },
+    execute,
+    terminal::{disable_raw_mode, supports_keyboard_enhancement},
+    Result,
+};
+use spin_sleep::LoopHelper;
+use std::io::{self, Stdout};
+use std::net::ToSocketAddrs;
+use std::time::Duration;
+use tetris::tetris::{MatchState, OnlineGame};
+use tui::{
+    backend::CrosstermBackend,
+    layout::{Constraint, Direction, Layout},
+    style::Color,
+    Terminal,
+};
+
+/// A match against another player through a `tetroxide server`, with the
+/// player's own game on the left and the opponent's on the right.
+pub struct Online {
+    game: OnlineGame,
+    callout: Option<Callout>,
+    /// If the terminal reports key releases.
+    releases: bool,
+}
+
+impl Online {
+    /// Connects to the server at `addr`, and waits for it to find an
+    /// opponent.
+    pub fn connect<A: ToSocketAddrs>(addr: A) -> Result<Self> {
+        Ok(Online {
+            game: OnlineGame::connect(addr)?,
+            callout: None,
+            releases: false,
+        })
+    }
+
+    fn render(&self, terminal: &mut Terminal<CrosstermBackend<Stdout>>) -> Result<()> {
+        let width = player_width(self.game.local());
+        terminal.draw(|f| {
+            let size = f.size();
+            let margin = size.width.saturating_sub(2 * width) / 2;
+            let all = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints(
+                    [
+                        Constraint::Length(margin),
+                        Constraint::Length(width),
+                        Constraint::Length(width),
+                        Constraint::Length(margin),
+                    ]
+                    .as_ref(),
+                )
+                .split(size);
+            let board_area = draw_player(
+                f,
+                all[1],
+                self.game.local(),
+                &self.callout,
+                "YOU",
+                self.releases,
+            );
+            // There's no telling if the opponent's terminal reports releases.
+            draw_player(f, all[2], self.game.opponent(), &None, "OPPONENT", false);
+            let countdown;
+            let (title, text, color) = match self.game.state() {
+                MatchState::Waiting => ("GET READY", "", Color::White),
+                MatchState::Countdown(seconds) => {
+                    countdown = seconds.to_string();
+                    ("GET READY", countdown.as_str(), Color::White)
+                }
+                MatchState::Playing => return,
+                MatchState::Won => ("WINNER", "Esc: quit", Color::Green),
+                MatchState::Lost => ("LOSER", "Esc: quit", Color::Red),
+                MatchState::Disconnected => ("DISCONNECTED", "Esc: quit", Color::Yellow),
+            };
+            draw_banner(f, board_area, title, text, color);
+        })?;
+        Ok(())
+    }
+
+    /// Plays the match, until the player quits with `Esc`. Quitting before
+    /// the match is over forfeits it.
+    async fn online_loop(
+        &mut self,
+        terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+    ) -> Result<()> {
+        let mut loop_helper = LoopHelper::builder().build_with_target_rate(60.0);
+        self.releases = supports_keyboard_enhancement().unwrap_or(false);
+        if self.releases {
+            execute!(
+                io::stdout(),
+                PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::REPORT_EVENT_TYPES)
+            )?;
+        }
+        'online: loop {
+            loop_helper.loop_start();
+            self.render(terminal)?;
+            let mut events = Vec::new();
+            while poll(Duration::from_secs(0))? {
+                events.push(read()?);
+            }
+            for event in events {
+                let Event::Key(KeyEvent { code, kind, .. }) = event else {
+                    continue;
+                };
+                if code == KeyCode::Esc && kind == KeyEventKind::Press {
+                    break 'online;
+                }
+                let Some(action) = key_action(code) else {
+                    continue;
+                };
+                if self.game.state() != MatchState::Playing {
+                    continue;
+                }
+                let tetris = self.game.local_mut();
+                match kind {
+                    KeyEventKind::Press if self.releases => tetris.set_held(action, true),
+                    KeyEventKind::Release => tetris.set_held(action, false),
+                    KeyEventKind::Press => {
+                        tetris.apply(action);
+                    }
+                    KeyEventKind::Repeat => {}
+                }
+            }
+            // Losing the server shows up in the match state, so the player
+            // can see what happened before quitting.
+            if let Ok(events) = self.game.step() {
+                show_events(self.game.local(), &events, &mut self.callout, &mut None);
+            }
+            loop_helper.loop_sleep();
+        }
+        if self.releases {
+            execute!(io::stdout(), PopKeyboardEnhancementFlags)?;
+        }
+        Ok(())
+    }
+
+    pub async fn run(&mut self) -> Result<()> {
+        let mut terminal = enter_terminal()?;
+        self.online_loop(&mut terminal).await?;
+        disable_raw_mode()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,234 @@
+use super::{draw_banner, draw_player, enter_terminal, player_width, show_events, Callout};
+use crossterm::{
+    event::{
+        poll, read, Event, KeyCode, KeyEvent, KeyEventKind, KeyboardEnhancementFlags,
+        PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+    },
+    execute,
+    terminal::{disable_raw_mode, supports_keyboard_enhancement},
+    Result,
+};
+use spin_sleep::LoopHelper;
+use std::io::{self, Stdout};
+use std::time::Duration;
+use tetris::tetris::{Action, Config, GameEvent, GameModeKind, Tetris};
+use tui::{
+    backend::CrosstermBackend,
+    layout::{Constraint, Direction, Layout},
+    style::Color,
+    Terminal,
+};
+
+/// The player a key belongs to, and the action it's bound to, if any. Player 1
+/// plays on the left of the keyboard, and player 2 on the right.
+fn versus_action(code: KeyCode) -> Option<(usize, Action)> {
+    match code {
+        KeyCode::Char('a') => Some((0, Action::ShiftLeft)),
+        KeyCode::Char('d') => Some((0, Action::ShiftRight)),
+        KeyCode::Char('w') => Some((0, Action::RotateCw)),
+        KeyCode::Char('q') => Some((0, Action::RotateCcw)),
+        KeyCode::Char('e') => Some((0, Action::Rotate180)),
+        KeyCode::Char('s') => Some((0, Action::SoftDrop)),
+        KeyCode::Char(' ') => Some((0, Action::HardDrop)),
+        KeyCode::Char('c') => Some((0, Action::Hold)),
+        KeyCode::Left => Some((1, Action::ShiftLeft)),
+        KeyCode::Right => Some((1, Action::ShiftRight)),
+        KeyCode::Up => Some((1, Action::RotateCw)),
+        KeyCode::Char('/') => Some((1, Action::RotateCcw)),
+        KeyCode::Char('.') => Some((1, Action::Rotate180)),
+        KeyCode::Down => Some((1, Action::SoftDrop)),
+        KeyCode::Enter => Some((1, Action::HardDrop)),
+        KeyCode::Char(',') => Some((1, Action::Hold)),
+        _ => None,
+    }
+}
+
+/// One side of a versus match.
+struct Player {
+    tetris: Tetris,
+    callout: Option<Callout>,
+}
+
+/// How a versus match ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Outcome {
+    /// The player at this index outlasted the other.
+    Won(usize),
+    /// Both players topped out on the same frame.
+    Draw,
+}
+
+/// Two players on one keyboard, side by side, sending each other garbage until
+/// one of them tops out.
+pub struct Versus {
+    config: Config,
+    players: [Player; 2],
+    outcome: Option<Outcome>,
+    /// If the terminal reports key releases.
+    releases: bool,
+}
+
+/// Starts a game for each player. Both games share a seed, so both players
+/// are dealt the same pieces.
+fn new_players(config: Config) -> [Player; 2] {
+    let first = Tetris::with_config(config, None);
+    let second = Tetris::with_config(config, Some(first.get_seed()));
+    [first, second].map(|tetris| Player {
+        tetris,
+        callout: None,
+    })
+}
+
+impl Versus {
+    /// Sets up a match played with `config`. Matches are always played in
+    /// endless mode, so they only end when someone tops out.
+    pub fn new(config: Config) -> Self {
+        let config = Config {
+            mode: GameModeKind::Endless,
+            ..config
+        };
+        Versus {
+            config,
+            players: new_players(config),
+            outcome: None,
+            releases: false,
+        }
+    }
+
+    /// Runs both games for a frame, sends each player's attacks to the other,
+    /// and checks if either has lost.
+    fn step(&mut self) {
+        let mut attacks = [0; 2];
+        for (i, player) in self.players.iter_mut().enumerate() {
+            player.tetris.frame_advance();
+            let events = player.tetris.take_events();
+            show_events(&player.tetris, &events, &mut player.callout, &mut None);
+            for event in events {
+                if let GameEvent::Attack(lines) = event {
+                    attacks[1 - i] += lines;
+                }
+            }
+        }
+        for (player, lines) in self.players.iter_mut().zip(attacks) {
+            if lines > 0 {
+                player.tetris.queue_garbage(lines);
+            }
+        }
+        self.outcome = match (
+            self.players[0].tetris.is_game_over,
+            self.players[1].tetris.is_game_over,
+        ) {
+            (true, true) => Some(Outcome::Draw),
+            (false, true) => Some(Outcome::Won(0)),
+            (true, false) => Some(Outcome::Won(1)),
+            (false, false) => None,
+        };
+    }
+
+    fn render(&self, terminal: &mut Terminal<CrosstermBackend<Stdout>>) -> Result<()> {
+        let width = player_width(&self.players[0].tetris);
+        terminal.draw(|f| {
+            let size = f.size();
+            let margin = size.width.saturating_sub(2 * width) / 2;
+            let all = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints(
+                    [
+                        Constraint::Length(margin),
+                        Constraint::Length(width),
+                        Constraint::Length(width),
+                        Constraint::Length(margin),
+                    ]
+                    .as_ref(),
+                )
+                .split(size);
+            for (i, player) in self.players.iter().enumerate() {
+                let title = format!("PLAYER {}", i + 1);
+                let board_area = draw_player(
+                    f,
+                    all[i + 1],
+                    &player.tetris,
+                    &player.callout,
+                    &title,
+                    self.releases,
+                );
+                let Some(outcome) = self.outcome else {
+                    continue;
+                };
+                let (banner, color) = match outcome {
+                    Outcome::Won(winner) if winner == i => ("WINNER", Color::Green),
+                    Outcome::Won(_) => ("LOSER", Color::Red),
+                    Outcome::Draw => ("DRAW", Color::Yellow),
+                };
+                draw_banner(f, board_area, banner, "Enter: rematch\nEsc: quit", color);
+            }
+        })?;
+        Ok(())
+    }
+
+    /// Plays matches until either player quits with `Esc`. Once a match is
+    /// over, `Enter` starts a rematch.
+    async fn versus_loop(
+        &mut self,
+        terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+    ) -> Result<()> {
+        let mut loop_helper = LoopHelper::builder().build_with_target_rate(60.0);
+        self.releases = supports_keyboard_enhancement().unwrap_or(false);
+        if self.releases {
+            execute!(
+                io::stdout(),
+                PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::REPORT_EVENT_TYPES)
+            )?;
+        }
+        'versus: loop {
+            loop_helper.loop_start();
+            self.render(terminal)?;
+            let mut events = Vec::new();
+            while poll(Duration::from_secs(0))? {
+                events.push(read()?);
+            }
+            for event in events {
+                let Event::Key(KeyEvent { code, kind, .. }) = event else {
+                    continue;
+                };
+                if code == KeyCode::Esc && kind == KeyEventKind::Press {
+                    break 'versus;
+                }
+                if self.outcome.is_some() {
+                    if code == KeyCode::Enter && kind == KeyEventKind::Press {
+                        self.players = new_players(self.config);
+                        self.outcome = None;
+                    }
+                    continue;
+                }
+                let Some((i, action)) = versus_action(code) else {
+                    continue;
+                };
+                let tetris = &mut self.players[i].tetris;
+                match kind {
+                    KeyEventKind::Press if self.releases => tetris.set_held(action, true),
+                    KeyEventKind::Release => tetris.set_held(action, false),
+                    KeyEventKind::Press => {
+                        tetris.apply(action);
+                    }
+                    KeyEventKind::Repeat => {}
+                }
+            }
+            if self.outcome.is_none() {
+                self.step();
+            }
+            loop_helper.loop_sleep();
+        }
+        if self.releases {
+            execute!(io::stdout(), PopKeyboardEnhancementFlags)?;
+        }
+        Ok(())
+    }
+
+    pub async fn run(&mut self) -> Result<()> {
+        let mut terminal = enter_terminal()?;
+        self.versus_loop(&mut terminal).await?;
+        disable_raw_mode()?;
+        Ok(())
+    }
+}